@@ -0,0 +1,78 @@
+use tcod;
+use tcod::{OffscreenConsole, RootConsole};
+use tcod::console;
+use tcod::console::Console;
+
+use master_of_destiny::draw::{Backend, BackgroundFlag, Color};
+
+fn color(c: Color) -> tcod::Color { tcod::Color::new(c.r, c.g, c.b) }
+
+fn flag(f: BackgroundFlag) -> console::BackgroundFlag {
+    match f {
+        BackgroundFlag::None => console::BackgroundFlag::None,
+        BackgroundFlag::Set => console::BackgroundFlag::Set,
+        BackgroundFlag::Darken => console::BackgroundFlag::Darken,
+        BackgroundFlag::Default => console::BackgroundFlag::Default,
+    }
+}
+
+/// Draws the game onto the tcod window.
+pub struct TcodBackend<'a>(pub &'a mut RootConsole);
+
+impl<'a> Backend for TcodBackend<'a> {
+    fn width(&self) -> i32 { self.0.width() }
+    fn height(&self) -> i32 { self.0.height() }
+    fn clear(&mut self) { self.0.clear() }
+
+    fn put_char(&mut self,
+                x: i32,
+                y: i32,
+                chr: char,
+                f: BackgroundFlag) {
+        self.0.put_char(x, y, chr, flag(f));
+    }
+
+    fn put_char_ex(&mut self,
+                   x: i32,
+                   y: i32,
+                   chr: char,
+                   fg: Color,
+                   bg: Color) {
+        self.0.put_char_ex(x, y, chr, color(fg), color(bg));
+    }
+
+    fn set_default_foreground(&mut self, c: Color) {
+        self.0.set_default_foreground(color(c));
+    }
+
+    fn set_char_foreground(&mut self, x: i32, y: i32, c: Color) {
+        self.0.set_char_foreground(x, y, color(c));
+    }
+
+    fn set_char_background(&mut self,
+                           x: i32,
+                           y: i32,
+                           c: Color,
+                           f: BackgroundFlag) {
+        self.0.set_char_background(x, y, color(c), flag(f));
+    }
+
+    fn panel(&mut self,
+             pos: (i32, i32),
+             size: (i32, i32),
+             title: &str,
+             lines: &[String]) {
+        let mut window = &OffscreenConsole::new(size.0, size.1);
+        window.print_frame(0,
+                           0,
+                           size.0,
+                           size.1,
+                           true,
+                           console::BackgroundFlag::Set,
+                           Some(title));
+        for (i, line) in lines.iter().enumerate() {
+            window.print(1, i as i32 + 1, line);
+        }
+        console::blit(window, (0, 0), size, self.0, pos, 1.0, 0.6);
+    }
+}
@@ -0,0 +1,19 @@
+extern crate master_of_destiny;
+
+use master_of_destiny::headless;
+use master_of_destiny::worldgen::MAP_SIZE;
+
+fn main() {
+    match headless::Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => {
+            print!("{}", headless::run(MAP_SIZE, &options))
+        }
+        Err(e) => {
+            println!("{}", e);
+            println!("Usage: headless [--seed N] [--ticks N] \
+                      [--snapshot-every N] [--snapshot-dir DIR] \
+                      [--replay FILE]");
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,23 +1,92 @@
 use std;
 use std::cell::RefCell;
 
-use tcod::{OffscreenConsole, RootConsole};
-use tcod::colors::Color;
-use tcod::console;
-use tcod::console::{BackgroundFlag, Console};
-
 use life::Living;
 
+use weather;
+
 use worldgen::{Frames, World, WorldState};
 use worldgen::terrain::{TILES, Tile};
 
+/// An RGB colour.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Color {
+        Color { r: r, g: g, b: b }
+    }
+}
+
+/// How a colour is combined with the background already drawn.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BackgroundFlag {
+    None,
+    Set,
+    Darken,
+    Default,
+}
+
+/// Code page 437 glyphs used when drawing without the tileset.
+pub mod chars {
+    pub const BLOCK1: char = '\u{b0}';
+    pub const BLOCK2: char = '\u{b1}';
+    pub const BLOCK3: char = '\u{b2}';
+    pub const CLUB: char = '\u{5}';
+    pub const BULLET: char = '\u{7}';
+    pub const RADIO_SET: char = '\u{a}';
+    pub const EXCLAM_DOUBLE: char = '\u{13}';
+    pub const ARROW_N: char = '\u{18}';
+    pub const YEN: char = '\u{9d}';
+    pub const CROSS: char = '\u{c5}';
+    pub const DCROSS: char = '\u{ce}';
+}
+
+/// Something the game can be drawn onto, such as a terminal console.
+/// The drawing code only talks to this, so the simulation does not
+/// depend on any one frontend.
+pub trait Backend {
+    fn width(&self) -> i32;
+    fn height(&self) -> i32;
+    fn clear(&mut self);
+    fn put_char(&mut self,
+                x: i32,
+                y: i32,
+                chr: char,
+                flag: BackgroundFlag);
+    fn put_char_ex(&mut self,
+                   x: i32,
+                   y: i32,
+                   chr: char,
+                   fg: Color,
+                   bg: Color);
+    fn set_default_foreground(&mut self, color: Color);
+    fn set_char_foreground(&mut self, x: i32, y: i32, color: Color);
+    fn set_char_background(&mut self,
+                           x: i32,
+                           y: i32,
+                           color: Color,
+                           flag: BackgroundFlag);
+    /// Draws a framed, see-through window with a title and one line
+    /// of text per row.
+    fn panel(&mut self,
+             pos: (i32, i32),
+             size: (i32, i32),
+             title: &str,
+             lines: &[String]);
+}
+
 pub trait DrawChar {
-    fn draw_char(&self, root: &mut RootConsole, pos: (usize, usize));
+    fn draw_char(&self, root: &mut Backend, pos: (usize, usize));
 }
 
 pub trait FramedDraw {
     fn draw_framed_char(&self,
-                        root: &mut RootConsole,
+                        root: &mut Backend,
                         pos: (usize, usize),
                         time: usize,
                         frames_hash: &Frames);
@@ -27,32 +96,37 @@ pub trait Describe {
     fn describe(&self) -> String;
 }
 
-fn draw_hud(root: &mut RootConsole,
+/// Draws the HUD panel on the right of the screen: the date, weather
+/// and orders, what is under the cursor, and the latest messages at
+/// the bottom, above the speed and menu buttons.
+fn draw_hud(root: &mut Backend,
             world: &WorldState,
             world_map: &World,
+            messages: &[String],
             wid: usize,
             hig: usize) {
     let frame_start_pos = (wid as i32 / 3) * 2 - 10;
     let frame_width = wid as i32 - frame_start_pos;
     let frame_height = hig as i32;
-    let mut window = &OffscreenConsole::new(frame_width,
-                                            frame_height);
-    window.print_frame(0,
-                       0,
-                       frame_width,
-                       frame_height,
-                       true,
-                       BackgroundFlag::Set,
-                       Some("Tools"));
-    let mut hud_info: [String; 8] =
+    let (done, total) = world.commands
+                             .iter()
+                             .filter_map(|o| world.jobs.progress(o))
+                             .fold((0, 0), |(d, t), (od, ot)| {
+                                 (d + od, t + ot)
+                             });
+    let mut hud_info: [String; 9] =
         [format!("Height: {}", world.level),
          format!("Screen Position: {}, {}",
                  world.screen.0,
                  world.screen.1),
          format!("Date: {}", world.time.calendar.describe()),
          format!("Clock: {}", world.time.clock.describe()),
-         format!("Weather: {:?}", world.time.calendar.weather),
+         format!("Weather: {}", world.weather.describe()),
          format!("Life #: {}", world_map.life.len()),
+         format!("Orders: {} ({}/{} jobs done)",
+                 world.commands.len(),
+                 done,
+                 total),
          String::new(),
          String::new()];
     let (cx, cy) = (world.cursor.0, world.cursor.1);
@@ -61,10 +135,26 @@ fn draw_hud(root: &mut RootConsole,
         let wmap = &world_map[cy][cx];
         let wmapt = wmap.tiles.borrow();
         let len = wmapt.len().checked_sub(1).unwrap_or(0);
-        hud_info[6] = if let Some((_id, life)) =
+        if let Some(ref biome) = wmap.biome {
+            let season = world.time.calendar.season;
+            let temp = weather::temperature(biome,
+                                            season,
+                                            &world.time.clock,
+                                            &world.weather);
+            hud_info[4] = format!("Weather: {}, {:.0}F",
+                                  world.weather.describe(),
+                                  temp);
+        }
+        hud_info[7] = if let Some((_id, life)) =
             world_map.life_at_point(cx, cy)
         {
-            format!("{:?}", life.borrow().species().species)
+            let life = life.borrow();
+            let species = life.species().species;
+            if life.sick() > 0 {
+                format!("{:?}, sick", species)
+            } else {
+                format!("{:?}", species)
+            }
         } else {
             if len < world.level as usize {
                 wmapt.get(len as usize)
@@ -75,24 +165,28 @@ fn draw_hud(root: &mut RootConsole,
             .describe()
         };
         if len != world.level as usize {
-            hud_info[7] = format!("Distance from Level: {}",
+            hud_info[8] = format!("Distance from Level: {}",
                                   world.level as i32 - len as i32);
         }
     }
-    for (i, line) in hud_info.iter().enumerate() {
-        window.print(1, i as i32 + 1, line);
+    // The frame and the row of buttons take up the bottom three rows.
+    let bottom = std::cmp::max(frame_height, 3) as usize - 3;
+    let mut lines = hud_info.to_vec();
+    while lines.len() + messages.len() < bottom {
+        lines.push(String::new());
     }
-    console::blit(window,
-                  (0, 0),
-                  (frame_width, frame_height),
-                  root,
-                  (frame_start_pos, 0),
-                  1.0,
-                  0.6);
+    lines.extend(messages.iter().map(|m| {
+        m.chars().take(frame_width as usize - 2).collect()
+    }));
+    root.panel((frame_start_pos, 0),
+               (frame_width, frame_height),
+               "Tools",
+               &lines);
 }
 
-pub fn draw_map(root: &mut RootConsole,
+pub fn draw_map(root: &mut Backend,
                 world: &WorldState,
+                messages: &[String],
                 show_hud: bool,
                 time: usize) {
     match world.map {
@@ -169,7 +263,7 @@ pub fn draw_map(root: &mut RootConsole,
                 }
             }
             if show_hud {
-                draw_hud(root, world, world_map, wid, hig);
+                draw_hud(root, world, world_map, messages, wid, hig);
             }
         }
         None => {}
@@ -179,7 +273,7 @@ pub fn draw_map(root: &mut RootConsole,
     }
 }
 
-fn draw_life(root: &mut RootConsole,
+fn draw_life(root: &mut Backend,
              ws: &WorldState,
              life: &Vec<RefCell<Box<Living>>>) {
     let wid = root.width();
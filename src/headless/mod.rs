@@ -0,0 +1,169 @@
+use std;
+
+use life::animal::Species;
+use replay;
+use replay::Replay;
+use save;
+use utils::Point2D;
+use worldgen::{World, WorldEvent, WorldState};
+use worldgen::terrain::{Item, ItemCategory, Tile};
+
+/// Settings for a simulation run without a window, read from the
+/// command line.
+pub struct Options {
+    pub seed: u32,
+    pub ticks: usize,
+    pub snapshot_every: Option<usize>,
+    pub snapshot_dir: String,
+    pub replay: Option<Replay>,
+}
+
+impl Options {
+    /// Reads `--seed`, `--ticks`, `--snapshot-every`, `--snapshot-dir`
+    /// and `--replay` from the command line arguments. A replay
+    /// brings its own seed.
+    pub fn from_args<I>(args: I) -> Result<Options, String>
+        where I: Iterator<Item = String>
+    {
+        let mut options = Options {
+            seed: 0,
+            ticks: 1000,
+            snapshot_every: None,
+            snapshot_dir: "snapshots".to_string(),
+            replay: None,
+        };
+        let mut args = args;
+        while let Some(flag) = args.next() {
+            let value = args.next()
+                            .ok_or(format!("{} needs a value", flag))?;
+            let number = value.parse::<usize>();
+            match flag.as_ref() {
+                "--seed" => {
+                    options.seed = value.parse::<u32>()
+                                        .map_err(|e| e.to_string())?
+                }
+                "--ticks" => {
+                    options.ticks = number.map_err(|e| e.to_string())?
+                }
+                "--snapshot-every" => {
+                    options.snapshot_every =
+                        Some(number.map_err(|e| e.to_string())?)
+                }
+                "--snapshot-dir" => options.snapshot_dir = value,
+                "--replay" => {
+                    let replay = replay::load(&value)
+                                     .map_err(|e| e.to_string())?;
+                    options.replay = Some(replay)
+                }
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// What happened over a headless run.
+pub struct Summary {
+    pub ticks: usize,
+    pub deaths: usize,
+    pub population: Vec<(Species, usize)>,
+    pub items: Vec<(ItemCategory, usize)>,
+}
+
+/// Counts the living actors of each species.
+fn population(world: &World) -> Vec<(Species, usize)> {
+    world.life.iter().fold(vec![], |mut counts, actor| {
+        let species = actor.borrow().species().species;
+        match counts.iter().position(|&(s, _)| s == species) {
+            Some(i) => counts[i].1 += 1,
+            None => counts.push((species, 1)),
+        }
+        counts
+    })
+}
+
+/// Counts every item lying anywhere on the map, stockpiled or not,
+/// by category.
+fn items(world: &World) -> Vec<(ItemCategory, usize)> {
+    let all = world.map
+                   .iter()
+                   .flat_map(|row| row.iter())
+                   .flat_map(|unit| {
+        unit.tiles
+            .borrow()
+            .iter()
+            .filter_map(|t| match t {
+                            &Tile::Item(i) => Some(i),
+                            _ => None,
+                        })
+            .collect::<Vec<Item>>()
+    })
+                   .collect::<Vec<_>>();
+    [ItemCategory::Food, ItemCategory::Material, ItemCategory::Tool]
+        .iter()
+        .map(|cat| {
+                 (*cat,
+                  all.iter().filter(|i| i.category() == *cat).count())
+             })
+        .collect()
+}
+
+/// Generates a world and steps it for the given number of ticks
+/// without drawing anything, playing back the player's inputs from a
+/// replay and dumping a save file every `snapshot_every` ticks if
+/// asked to.
+pub fn run(size: Point2D, options: &Options) -> Summary {
+    let seed = options.replay
+                      .as_ref()
+                      .map_or(options.seed, |r| r.seed);
+    let mut ws = WorldState::new();
+    ws.add_map(World::new(size, seed));
+
+    let mut deaths = 0;
+    for tick in 0..options.ticks {
+        let events = match options.replay {
+            Some(ref replay) => replay.play_into(&mut ws, 1),
+            None => ws.update(),
+        };
+        deaths += events.iter()
+                        .filter(|e| matches!(**e, WorldEvent::Death(..)))
+                        .count();
+
+        if let Some(every) = options.snapshot_every {
+            if every > 0 && (tick + 1) % every == 0 {
+                let path = format!("{}/tick-{}.json",
+                                   options.snapshot_dir,
+                                   tick + 1);
+                if let Err(e) = save::save(&ws, &path) {
+                    println!("Could not write snapshot {}: {}",
+                             path,
+                             e);
+                }
+            }
+        }
+    }
+
+    let world = ws.map.as_ref().unwrap();
+    Summary {
+        ticks: options.ticks,
+        deaths: deaths,
+        population: population(world),
+        items: items(world),
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Ran {} ticks", self.ticks)?;
+        writeln!(f, "Deaths: {}", self.deaths)?;
+        writeln!(f, "Population:")?;
+        for &(species, count) in self.population.iter() {
+            writeln!(f, "    {:?}: {}", species, count)?;
+        }
+        writeln!(f, "Items on the map:")?;
+        for &(category, count) in self.items.iter() {
+            writeln!(f, "    {:?}: {}", category, count)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,372 @@
+use std::mem;
+
+use life::{Mission, Order};
+use physics::PhysicsActor;
+use utils::{Point2D, Point3D, Rect2D, in_rect, rect_points,
+            rects_overlap};
+use worldgen::{World, WorldEvent, WorldState};
+use worldgen::stockpile::Stockpile;
+use worldgen::terrain::{State, Tile, VegType};
+
+/// Priority of player orders. Workers will see to their own needs
+/// before carrying out orders.
+pub const ORDER_PRIORITY: usize = 50;
+/// How many ticks a job waits in the queue after a worker gave up on
+/// it, before it is handed out again.
+const RETRY_DELAY: usize = 200;
+/// How many times workers can give up on a job before it is dropped
+/// as something that can not be done.
+const MAX_FAILURES: usize = 3;
+/// How often, in ticks, the map is searched for loose items to haul
+/// into stockpiles.
+const HAUL_INTERVAL: usize = 100;
+
+/// One tile's worth of work from a player order.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub parent: Order,
+    pub order: Order,
+    pub target: Point3D,
+    pub assigned: bool,
+    pub delay: usize,
+    /// How many times a worker has given up on it.
+    #[serde(default)]
+    pub failures: usize,
+}
+
+/// Keeps track of the jobs that player orders have been split into,
+/// and of how many jobs each order started out with.
+#[derive(Serialize, Deserialize)]
+pub struct JobBoard {
+    pub jobs: Vec<Job>,
+    pub totals: Vec<(Order, usize)>,
+    pub tick: usize,
+}
+
+impl JobBoard {
+    pub fn new() -> JobBoard {
+        JobBoard {
+            jobs: vec![],
+            totals: vec![],
+            tick: 0,
+        }
+    }
+
+    /// Returns how many of an order's jobs are done, out of how many
+    /// there are in total.
+    pub fn progress(&self, order: &Order) -> Option<(usize, usize)> {
+        self.totals
+            .iter()
+            .find(|&&(o, _)| o == *order)
+            .map(|&(_, total)| {
+                let left = self.jobs
+                               .iter()
+                               .filter(|j| j.parent == *order)
+                               .count();
+                (total - left, total)
+            })
+    }
+}
+
+/// Finds the topmost solid stone of a column, as long as there is
+/// no water on top of it. The bottom of the world can not be dug.
+fn mineable((x, y): Point2D, world: &World) -> Option<Point3D> {
+    let unit = get!(world.unit_at((x, y)));
+    let tiles = unit.tiles.borrow();
+    let z = get!(tiles.iter().rposition(|t| t.solid()));
+    let flooded = tiles[z + 1..].iter().any(|t| World::is_water(*t));
+    match tiles[z] {
+        Tile::Stone(_, State::Solid) if z > 0 && !flooded => {
+            Some((x, y, z))
+        }
+        _ => None,
+    }
+}
+
+/// Finds the open ground of a column, if it is clear enough to build
+/// on or to put things down. Only low plants can be covered up.
+fn clear_ground((x, y): Point2D, world: &World) -> Option<Point3D> {
+    let z = world.location_z((x, y));
+    match get!(world.tile_at((x, y, z))) {
+        Tile::Empty => Some((x, y, z)),
+        Tile::Vegetation(_, h, _) if h <= 3 => Some((x, y, z)),
+        _ => None,
+    }
+}
+
+/// Finds the plant on top of a column, if it is of the right kind.
+fn plant<F>((x, y): Point2D, world: &World, test: F) -> Option<Point3D>
+    where F: Fn(VegType) -> bool
+{
+    let z = world.location_z((x, y));
+    match get!(world.tile_at((x, y, z))) {
+        Tile::Vegetation(vt, ..) if test(vt) => Some((x, y, z)),
+        _ => None,
+    }
+}
+
+/// Finds the structure on top of a column, if there is one.
+fn construction((x, y): Point2D, world: &World) -> Option<Point3D> {
+    let unit = get!(world.unit_at((x, y)));
+    let tiles = unit.tiles.borrow();
+    let z = get!(tiles.iter().rposition(|t| {
+        t.solid() || matches!(*t, Tile::Construction(..))
+    }));
+    match tiles[z] {
+        Tile::Construction(..) => Some((x, y, z)),
+        _ => None,
+    }
+}
+
+/// Finds the tile that a single-tile order works on, or None if there
+/// is nothing to do there.
+pub fn target(order: &Order, world: &World) -> Option<Point3D> {
+    match order {
+        &Order::Go(p) => Some(p),
+        &Order::Mine((pnt, _)) => mineable(pnt, world),
+        &Order::BuildWall((pnt, _)) |
+        &Order::BuildFence((pnt, _)) |
+        &Order::BuildRamp(pnt) |
+        &Order::BuildStairs(pnt) |
+        &Order::CartGoods((pnt, _), _) => clear_ground(pnt, world),
+        &Order::FellTrees((pnt, _)) => {
+            plant(pnt, world, |v| v.is_tree())
+        }
+        &Order::GatherPlants((pnt, _)) => {
+            plant(pnt, world, |v| v.is_herb())
+        }
+        &Order::Destroy((pnt, _)) => construction(pnt, world),
+    }
+}
+
+/// Tests if there is still work to do for a job.
+fn needs_work(job: &Job, world: &World) -> bool {
+    match job.order {
+        Order::Go((x, y, _)) => world.life_at_point(x, y).is_none(),
+        Order::Mine(_) => {
+            world.tile_at(job.target).map_or(false, |t| {
+                matches!(t, Tile::Stone(_, State::Solid))
+            })
+        }
+        Order::BuildWall(_) |
+        Order::BuildFence(_) |
+        Order::BuildRamp(_) |
+        Order::BuildStairs(_) |
+        Order::FellTrees(_) |
+        Order::GatherPlants(_) |
+        Order::CartGoods(..) |
+        Order::Destroy(_) => {
+            target(&job.order, world) == Some(job.target)
+        }
+    }
+}
+
+/// Splits an order into a job for each tile it covers that has work
+/// to do. Hauling jobs are not known up front, since they depend on
+/// the loose items around, so they are queued separately by `haul`.
+fn split(order: &Order, world: &World) -> Vec<Job> {
+    if let &Order::CartGoods(..) = order {
+        return vec![];
+    }
+    rect_points(order.area())
+        .into_iter()
+        .filter_map(|pnt| {
+            let single = order.at(pnt);
+            target(&single, world).map(|t| {
+                Job {
+                    parent: *order,
+                    order: single,
+                    target: t,
+                    assigned: false,
+                    delay: 0,
+                    failures: 0,
+                }
+            })
+        })
+        .collect()
+}
+
+fn distance_to((x1, y1, z1): Point3D, (x2, y2, z2): Point3D) -> isize {
+    let (dx, dy, dz) = (x1 as isize - x2 as isize,
+                        y1 as isize - y2 as isize,
+                        z1 as isize - z2 as isize);
+    dx * dx + dy * dy + dz * dz
+}
+
+/// Queues hauling jobs for the free cells of each stockpile, up to the
+/// number of loose items around that the stockpile would take. While
+/// the order that set up the stockpile is still pending, the jobs
+/// count towards its progress.
+fn haul(board: &mut JobBoard, world: &World) {
+    let loose = world.loose_items();
+    for pile in world.stockpiles.iter() {
+        let order = pile.order();
+        let wanted = loose.iter()
+                          .filter(|&&(_, ref item)| pile.accepts(item))
+                          .count();
+        let queued = board.jobs
+                          .iter()
+                          .filter(|j| j.parent == order)
+                          .count();
+        let new = rect_points(pile.area)
+            .into_iter()
+            .filter_map(|pnt| {
+                let single = order.at(pnt);
+                target(&single, world).map(|t| (single, t))
+            })
+            .filter(|&(_, t)| board.jobs.iter().all(|j| j.target != t))
+            .take(wanted.saturating_sub(queued))
+            .map(|(single, t)| {
+                Job {
+                    parent: order,
+                    order: single,
+                    target: t,
+                    assigned: false,
+                    delay: 0,
+                    failures: 0,
+                }
+            })
+            .collect::<Vec<_>>();
+        if let Some(total) = board.totals
+                                  .iter_mut()
+                                  .find(|&&mut (o, _)| o == order)
+        {
+            total.1 += new.len();
+        }
+        board.jobs.extend(new);
+    }
+}
+
+/// Runs the job dispatcher for one tick:
+/// * new orders are split into one job per tile, and new stockpiles
+///   are set up
+/// * jobs that no worker holds anymore are either done, or go back
+///   into the queue (when the worker died or gave up), unless they
+///   have failed too often, in which case they are dropped
+///   and reported
+/// * every so often, hauling jobs are queued for the stockpiles
+/// * queued jobs are handed to the closest worker able to do them
+/// * orders with no jobs left are taken off the command list, and
+///   reported as done. Stockpiles stay until they are called off,
+///   since there is always more to haul.
+pub fn run(ws: &mut WorldState) -> Vec<WorldEvent> {
+    if let Some(ref mut world) = ws.map {
+        let board = &mut ws.jobs;
+        for order in ws.commands.iter() {
+            if board.totals.iter().all(|&(o, _)| o != *order) {
+                if let &Order::CartGoods(area, filter) = order {
+                    world.add_stockpile(Stockpile::new(area, filter));
+                }
+                let jobs = split(order, world);
+                board.totals.push((*order, jobs.len()));
+                board.jobs.extend(jobs);
+            }
+        }
+
+        let held = world.life
+                        .iter()
+                        .flat_map(|l| l.borrow().orders())
+                        .collect::<Vec<_>>();
+        let mut events = vec![];
+        for job in mem::replace(&mut board.jobs, vec![]) {
+            let holder = held.contains(&job.order);
+            if !holder && !needs_work(&job, world) {
+                continue;
+            }
+            if job.assigned && !holder {
+                if job.failures + 1 >= MAX_FAILURES {
+                    events.push(WorldEvent::GaveUp(job.order));
+                } else {
+                    board.jobs.push(Job {
+                                        assigned: false,
+                                        delay: RETRY_DELAY,
+                                        failures: job.failures + 1,
+                                        ..job
+                                    });
+                }
+            } else {
+                let delay = job.delay.saturating_sub(1);
+                board.jobs.push(Job { delay: delay, ..job });
+            }
+        }
+
+        if board.tick % HAUL_INTERVAL == 0 {
+            haul(board, world);
+        }
+        board.tick += 1;
+
+        for job in board.jobs
+                        .iter_mut()
+                        .filter(|j| !j.assigned && j.delay == 0)
+        {
+            let worker = world.life
+                              .iter()
+                              .filter(|l| l.borrow().can_obey(&job.order))
+                              .min_by_key(|l| {
+                                  distance_to(l.borrow().current_pos(),
+                                              job.target)
+                              });
+            if let Some(w) = worker {
+                w.borrow_mut()
+                 .add_goal(Mission::Obey(ORDER_PRIORITY, job.order));
+                job.assigned = true;
+            }
+        }
+
+        let finished = board.totals
+                            .iter()
+                            .map(|&(o, _)| o)
+                            .filter(|o| match *o {
+                                        Order::CartGoods(..) => false,
+                                        _ => true,
+                                    })
+                            .filter(|o| {
+                                        board.jobs
+                                             .iter()
+                                             .all(|j| j.parent != *o)
+                                    })
+                            .collect::<Vec<_>>();
+        board.totals.retain(|&(o, _)| !finished.contains(&o));
+        ws.commands.retain(|o| !finished.contains(o));
+        events.extend(finished.into_iter()
+                              .map(WorldEvent::OrderDone));
+        events
+    } else {
+        vec![]
+    }
+}
+
+/// Calls off all work in an area: every order overlapping it, along
+/// with the jobs of those orders and any other jobs inside it, and the
+/// stockpiles in it. Workers busy with those jobs drop them.
+pub fn cancel(ws: &mut WorldState, area: Rect2D) {
+    let overlaps = |o: &Order| rects_overlap(o.area(), area);
+    let board = &mut ws.jobs;
+    let cancelled = board.jobs
+                         .iter()
+                         .filter(|j| {
+                                     overlaps(&j.parent) ||
+                                         in_rect((j.target.0, j.target.1),
+                                                 area)
+                                 })
+                         .map(|j| j.order)
+                         .collect::<Vec<_>>();
+    board.jobs.retain(|j| !cancelled.contains(&j.order));
+    board.totals.retain(|&(ref o, _)| !overlaps(o));
+    ws.commands.retain(|o| !overlaps(o));
+
+    if let Some(ref mut world) = ws.map {
+        world.stockpiles.retain(|s| !rects_overlap(s.area, area));
+        for actor in world.life.iter() {
+            let held = actor.borrow()
+                            .orders()
+                            .into_iter()
+                            .filter(|o| cancelled.contains(o))
+                            .collect::<Vec<_>>();
+            for order in held {
+                actor.borrow_mut()
+                     .remove_goal(&Mission::Obey(ORDER_PRIORITY, order));
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+#![feature(box_syntax,
+           box_patterns,
+           vec_remove_item,
+           conservative_impl_trait,
+           exclusive_range_pattern,
+           const_fn)]
+#![allow(dead_code)]
+
+//! The simulation behind Master of Destiny: world generation, life,
+//! player orders, physics and time, plus drawing through the
+//! `draw::Backend` trait. Nothing here depends on a particular
+//! frontend, so the game, the headless runner and tests all share it.
+
+#[macro_use]
+extern crate pipeline;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+#[macro_export]
+macro_rules! matches {
+    ($e:expr, $p:pat) => (
+        match $e {
+            $p => true,
+            _ => false
+        }
+    )
+}
+
+#[macro_export]
+macro_rules! get(
+    ($e:expr) => (match $e { Some(e) => e, None => return None })
+);
+
+pub mod utils;
+
+pub mod pathfinding;
+
+pub mod life;
+
+pub mod jobs;
+
+pub mod draw;
+
+pub mod physics;
+
+pub mod random;
+
+pub mod replay;
+
+pub mod headless;
+
+pub mod save;
+
+pub mod worldgen;
+
+pub mod time;
+
+pub mod weather;
@@ -3,12 +3,15 @@ use self::rand::Rng;
 
 use std;
 
-use life::{Living, Mission, MissionResult};
+use life::{Creature, Living, Mission, MissionResult};
+use pathfinding::find_path;
 use physics::PhysicsActor;
-use utils::{Point3D, distance, find_path, nearest_perimeter_point,
-            random_point, strict_adjacent};
+use random::Stream;
+use utils::{Point3D, distance, nearest_perimeter_point,
+            strict_3d_adjacent, strict_adjacent};
 use worldgen::World;
-use worldgen::terrain::{Biome, BiomeType, Food, Item, Tile, VegType};
+use worldgen::terrain::{Biome, BiomeType, Food, Item, LiquidPurity,
+                        State, Tile, VegType};
 
 const THIRST_THRESHOLD: i32 = 3000;
 const HUNGER_THRESHOLD: i32 = 6800;
@@ -22,7 +25,7 @@ macro_rules! matches {
     )
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Carnivore {
     Dog,
     Cat,
@@ -31,7 +34,7 @@ pub enum Carnivore {
     Alligator,
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Herbivore {
     Cow,
     Sheep,
@@ -43,12 +46,14 @@ pub enum Herbivore {
 }
 
 /// Possible animal species.
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Species {
     Carnivore(Carnivore),
     Herbivore(Herbivore),
+    Dwarf,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SpeciesProperties {
     pub health: i32,
     pub chr: char,
@@ -193,11 +198,21 @@ impl Species {
                     mood: super::Mood::Wary,
                 }
             }
+            &Dwarf => {
+                SpeciesProperties {
+                    health: 300,
+                    chr: '@',
+                    species: self.clone(),
+                    sight: 20,
+                    mood: super::Mood::Contented,
+                }
+            }
         }
     }
 }
 
 /// The animal itself. It keeps track of all its mental and physical states, as well as its goals.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Animal {
     thirst: i32,
     hunger: i32,
@@ -205,6 +220,9 @@ pub struct Animal {
     path: Option<Vec<Point3D>>,
     arrived: bool,
     failed_goal: Option<super::Mission>,
+    /// Ticks of sickness left from bad water.
+    #[serde(default)]
+    sick: usize,
     pub species: SpeciesProperties,
     pub pos: (usize, usize, usize),
     pub current_goal: Option<super::Mission>,
@@ -219,6 +237,7 @@ impl Animal {
                      path: None,
                      arrived: false,
                      failed_goal: None,
+                     sick: 0,
                      pos: pnt,
                      current_goal: None,
                      species: species.properties(),
@@ -263,7 +282,9 @@ impl Animal {
                          map: &World,
                          pnt: Point3D,
                          mission: Mission) {
-        if let Some(path) = self.create_path_to(map, pnt) {
+        if let Some(mut path) = self.create_path_to(map, pnt) {
+            // The path is followed by popping points off the end.
+            path.reverse();
             self.path = Some(path);
         } else {
             self.current_goal = None;
@@ -273,7 +294,8 @@ impl Animal {
 
     fn set_up_new_goal(&mut self,
                        map: &World,
-                       in_sight: Vec<(Tile, Point3D)>)
+                       in_sight: Vec<(Tile, Point3D)>,
+                       rng: &mut Stream)
         -> MissionResult {
         use self::Mission::*;
         if let Some(m) = self.current_goal {
@@ -303,9 +325,8 @@ impl Animal {
                     }
                     // If we need to drink, find the shoreline.
                     Drink(_) => {
-                        let mut trng = self::rand::thread_rng();
                         if let Some(pnt) =
-                            trng.choose(&map.biome_map["w"].borrow())
+                            rng.choose(&map.biome_map["w"].borrow())
                         {
                             let in_sight = strict_adjacent(*pnt);
                             let shore = in_sight.iter()
@@ -356,6 +377,25 @@ impl Animal {
                             );
                         MissionResult::NoResult
                     }
+                    // Run the other way from danger, as far as can
+                    // be seen.
+                    Flee(from, _) => {
+                        let sight = self.species.sight as isize;
+                        let away = |p: usize, f: usize, size: usize| {
+                            let (p, f) = (p as isize, f as isize);
+                            let to = p + (p - f).signum() * sight;
+                            to.max(0).min(size as isize - 1) as usize
+                        };
+                        let (w, h) = map.map_size;
+                        let x = away(self.pos.0, from.0, w);
+                        let y = away(self.pos.1, from.1, h);
+                        self.add_path_to_point(
+                            map,
+                            (x, y, map.location_z((x, y))),
+                            m
+                        );
+                        MissionResult::NoResult
+                    }
                     // go to a point
                     Go(point, _) => {
                         self.add_path_to_point(
@@ -371,7 +411,7 @@ impl Animal {
                 };
             }
         } else {
-            self.auto_add_mission(map, in_sight);
+            self.auto_add_mission(map, in_sight, rng);
             return MissionResult::NoResult;
         }
         self.failed_goal = self.current_goal;
@@ -379,12 +419,15 @@ impl Animal {
         MissionResult::NoResult
     }
 
-    fn satisfy_current_goal(&mut self, map: &World) -> MissionResult {
+    fn satisfy_current_goal(&mut self,
+                            map: &World,
+                            rng: &mut Stream)
+        -> MissionResult {
         let in_sight = self.in_sight(map);
 
         if self.path.is_none() && !self.arrived {
             // If we're not in the process of doing anything right now...
-            self.set_up_new_goal(map, in_sight)
+            self.set_up_new_goal(map, in_sight, rng)
         } else if self.path.is_some() {
             self.hunger += 10;
             self.thirst += 10;
@@ -446,6 +489,10 @@ impl Animal {
                 {
                     self.thirst /= 4;
                     MissionResult::ReplaceItem(pnt, Item::Food(Food::Water(q / 4)))
+                } else if let Some(purity) = self.water_nearby(map) {
+                    self.thirst /= 4;
+                    self.sicken(purity);
+                    MissionResult::NoResult
                 } else {
                     MissionResult::NoResult
                 }
@@ -483,6 +530,12 @@ impl Animal {
                                 MissionResult::NoResult
                             }
                         }
+                        // Dwarves eat by their own rules, not as
+                        // animals.
+                        Species::Dwarf => {
+                            self.failed_goal = Some(Mission::Eat(p));
+                            MissionResult::NoResult
+                        }
                     }
                 } else {
                     self.failed_goal = Some(Mission::Eat(p));
@@ -500,6 +553,21 @@ impl Animal {
     }
 
     fn tolerance(&self) -> i32 { 800 }
+
+    /// The purity of the water beside the animal, if there is any
+    /// there to drink.
+    fn water_nearby(&self, map: &World) -> Option<LiquidPurity> {
+        strict_3d_adjacent(self.pos, map)
+            .into_iter()
+            .filter_map(|p| map.tile_at(p))
+            .filter_map(|t| match t {
+                            Tile::Water(p, State::Liquid, _) => {
+                                Some(p)
+                            }
+                            _ => None,
+                        })
+            .next()
+    }
 }
 
 impl Living for Animal {
@@ -509,6 +577,23 @@ impl Living for Animal {
             self.current_goal = Some(Mission::Die);
             self.species.health = 0;
             self.goals = vec![];
+        } else if matches!(mission, Mission::Flee(..)) {
+            // Running from danger comes before anything else, but the
+            // dying stay put.
+            match self.current_goal {
+                Some(Mission::Die) => return,
+                Some(current) => {
+                    let found = self.goals.binary_search(&current);
+                    if let Err(i) = found {
+                        self.goals.insert(i, current);
+                    }
+                }
+                None => {}
+            }
+            self.failed_goal = None;
+            self.path = None;
+            self.arrived = false;
+            self.current_goal = Some(mission);
         } else {
             self.failed_goal = None;
             match self.goals.binary_search(&mission) {
@@ -536,24 +621,29 @@ impl Living for Animal {
         }
     }
 
-    fn execute_mission(&mut self, map: &World) -> MissionResult {
+    fn execute_mission(&mut self,
+                       map: &World,
+                       rng: &mut Stream)
+        -> MissionResult {
+        self.suffer();
         if self.current_goal.is_some() {
-            self.satisfy_current_goal(map)
+            self.satisfy_current_goal(map, rng)
         } else {
             let m = self.goals.pop();
             if m.is_some() {
                 self.current_goal = m;
             } else {
                 let is = self.in_sight(map);
-                self.auto_add_mission(map, is);
+                self.auto_add_mission(map, is, rng);
             }
-            self.satisfy_current_goal(map)
+            self.satisfy_current_goal(map, rng)
         }
     }
 
     fn auto_add_mission(&mut self,
                         map: &World,
-                        in_sight: Vec<(Tile, Point3D)>)
+                        in_sight: Vec<(Tile, Point3D)>,
+                        rng: &mut Stream)
         -> Option<Mission> {
         if self.thirst >= THIRST_THRESHOLD ||
             self.hunger >= HUNGER_THRESHOLD
@@ -594,7 +684,6 @@ impl Living for Animal {
             match self.species.species {
                 // Fish group to confuse enemies
                 Species::Herbivore(Herbivore::Fish) => {
-                    let mut trng = self::rand::thread_rng();
                     let fish = &map.life
                                    .iter()
                                    .filter_map(
@@ -625,7 +714,7 @@ impl Living for Animal {
                                          Species::Carnivore(..))
                             {
                                 if let Some(goal) =
-                                    trng.choose(&fish)
+                                    rng.choose(&fish)
                                 {
                                     self.add_goal(Mission::Go((goal.0,
                                                                goal.1),
@@ -638,7 +727,6 @@ impl Living for Animal {
                 // Whales like to form schools. That should become an
                 // emergant property of this.
                 Species::Herbivore(Herbivore::Whale) => {
-                    let mut trng = self::rand::thread_rng();
                     let whales =
                         &map.life
                             .iter()
@@ -656,12 +744,12 @@ impl Living for Animal {
                             },
                         )
                             .collect::<Vec<_>>();
-                    if let Some(goal) = trng.choose(whales) {
+                    if let Some(goal) = rng.choose(whales) {
                         self.add_goal(Mission::Go((goal.0, goal.1),
                                                   10));
                     } else {
                         let &(x, y, _) =
-                            trng.choose(&in_sight.iter()
+                            rng.choose(&in_sight.iter()
                                         .map(|&(_, x)| x)
                                         .collect::<Vec<_>>())
                             .unwrap();
@@ -670,11 +758,10 @@ impl Living for Animal {
                 }
                 _ => {
                     // Wander
-                    let mut trng = self::rand::thread_rng();
                     let &(x, y, _) =
-                        trng.choose(&in_sight.iter()
-                                             .map(|&(_, x)| x)
-                                             .collect::<Vec<_>>())
+                        rng.choose(&in_sight.iter()
+                                            .map(|&(_, x)| x)
+                                            .collect::<Vec<_>>())
                             .unwrap();
                     self.add_goal(Mission::Go((x, y), 12));
                 }
@@ -683,9 +770,22 @@ impl Living for Animal {
         self.current_goal
     }
 
+    fn hurt(&mut self, damage: i32) {
+        self.species.health -= damage;
+        if self.species.health <= 0 {
+            self.add_goal(Mission::Die);
+        }
+    }
+
+    fn sick(&self) -> usize { self.sick }
+    fn sick_mut(&mut self) -> &mut usize { &mut self.sick }
+
     fn current_pos(&self) -> Point3D { self.pos }
     fn current_goal(&self) -> Option<Mission> { self.current_goal }
     fn species(&self) -> &SpeciesProperties { &self.species }
+    fn snapshot(&self) -> Creature {
+        Creature::Animal(self.clone())
+    }
     fn goals(&self) -> (Option<&Mission>, Option<&Mission>) {
         (self.current_goal.as_ref(), self.failed_goal.as_ref())
     }
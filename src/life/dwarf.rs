@@ -1 +1,1125 @@
+extern crate rand;
+use self::rand::Rng;
 
+use std::cmp;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use jobs;
+use life::{Creature, Drinkable, Living, Mission, MissionResult,
+           Order, sickness};
+use life::animal::{Species, SpeciesProperties};
+use pathfinding::find_path;
+use physics::PhysicsActor;
+use physics::liquid::{Container, Draught};
+use random::Stream;
+use utils::{Point3D, strict_3d_adjacent, strict_adjacent};
+use worldgen::World;
+use worldgen::terrain::{BiomeType, Depth, Food, Item,
+                        LiquidPurity, Material, State, Structure,
+                        Tile, Tool, VegType};
+
+const THIRST_THRESHOLD: i32 = 5000;
+const HUNGER_THRESHOLD: i32 = 9000;
+const SLEEP_THRESHOLD: i32 = 12000;
+const TOLERANCE: i32 = 1500;
+const REST_RATE: i32 = 40;
+const RETRY_DELAY: usize = 100;
+/// Ticks of work it takes an untrained dwarf to dig out one unit of
+/// hardness with a pickaxe.
+const DIG_TIME: usize = 30;
+/// Ticks of work it takes an untrained dwarf to put up a structure.
+const BUILD_TIME: usize = 60;
+/// Ticks of work it takes an untrained dwarf to take down a structure.
+const DESTROY_TIME: usize = 30;
+/// The chance, for each level of skill in the trade of its material,
+/// that a dwarf salvages the material of a structure it takes down
+/// rather than breaking it.
+const SALVAGE_CHANCE: f32 = 0.3;
+/// Ticks of work it takes an untrained dwarf to cut through one
+/// level of a tree's height.
+const FELL_TIME: usize = 10;
+/// Ticks of work it takes an untrained dwarf to pick a plant.
+const GATHER_TIME: usize = 20;
+/// How many times longer work takes without the right tool.
+const NO_TOOL_PENALTY: usize = 3;
+/// The deepest water a dwarf will wade through. Anything deeper they
+/// go around.
+const WADE_DEPTH: Depth = 2;
+
+const DRINK_PRIORITY: usize = 300;
+const EAT_PRIORITY: usize = 200;
+const SLEEP_PRIORITY: usize = 100;
+
+/// Kinds of work a dwarf can be trained in. A dwarf that does not
+/// know a skill at all will not be given jobs that need it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize,
+         Deserialize)]
+pub enum Skill {
+    Mining,
+    Masonry,
+    Carpentry,
+    Woodcutting,
+    Herbalism,
+    Hauling,
+}
+
+/// The settlers. Like animals, they keep track of their bodily needs
+/// and goals, but they also carry an inventory and know a set of
+/// skills, which decide what player orders they can carry out and
+/// how fast.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Dwarf {
+    thirst: i32,
+    hunger: i32,
+    sleep: i32,
+    goals: Vec<Mission>,
+    path: Option<Vec<Point3D>>,
+    arrived: bool,
+    failed_goal: Option<Mission>,
+    retry: usize,
+    target: Option<Point3D>,
+    progress: usize,
+    carrying: Option<Item>,
+    /// Ticks of sickness left from bad water.
+    #[serde(default)]
+    sick: usize,
+    pub name: String,
+    pub inventory: Vec<Item>,
+    pub skills: HashMap<Skill, usize>,
+    pub species: SpeciesProperties,
+    pub pos: Point3D,
+    pub current_goal: Option<Mission>,
+}
+
+impl Dwarf {
+    pub fn new(pnt: Point3D,
+               name: &str,
+               skills: Vec<(Skill, usize)>,
+               inventory: Vec<Item>)
+        -> Box<Living> {
+        Box::new(Dwarf::fresh(pnt, name, skills, inventory))
+    }
+
+    fn fresh(pnt: Point3D,
+             name: &str,
+             skills: Vec<(Skill, usize)>,
+             inventory: Vec<Item>)
+        -> Dwarf {
+        Dwarf {
+            thirst: 0,
+            hunger: 0,
+            sleep: 0,
+            goals: vec![],
+            path: None,
+            arrived: false,
+            failed_goal: None,
+            retry: 0,
+            target: None,
+            progress: 0,
+            carrying: None,
+            sick: 0,
+            name: name.to_string(),
+            inventory: inventory,
+            skills: skills.into_iter().collect(),
+            species: Species::Dwarf.properties(),
+            pos: pnt,
+            current_goal: None,
+        }
+    }
+
+    /// The starting party: a miner, a woodcutter and a gatherer, each
+    /// with the tools of their trade and a little food.
+    pub fn party(pnts: Vec<Point3D>) -> Vec<Box<Living>> {
+        let ration = Item::Food(Food::Herb(VegType::Dandelion));
+        let templates =
+            vec![("Urist",
+                  vec![(Skill::Mining, 3),
+                       (Skill::Masonry, 1),
+                       (Skill::Hauling, 1)],
+                  vec![Item::Tool(Tool::Pickaxe, 6, 3, None)]),
+                 ("Kadol",
+                  vec![(Skill::Woodcutting, 3),
+                       (Skill::Carpentry, 2),
+                       (Skill::Hauling, 1)],
+                  vec![Item::Tool(Tool::Knife, 1, 1, None),
+                       Item::vessel(Tool::Bucket)]),
+                 ("Litast",
+                  vec![(Skill::Herbalism, 3),
+                       (Skill::Masonry, 2),
+                       (Skill::Hauling, 2)],
+                  vec![Item::Tool(Tool::Hammer, 3, 2, None),
+                       Item::vessel(Tool::Bowl)])];
+        pnts.into_iter()
+            .zip(templates.into_iter())
+            .map(|(pnt, (name, skills, mut inventory))| {
+                inventory.push(ration);
+                inventory.push(ration);
+                Dwarf::new(pnt, name, skills, inventory)
+            })
+            .collect()
+    }
+
+    /// The skill level the dwarf has in a certain kind of work, or
+    /// None if it has not been trained in it at all.
+    pub fn skill(&self, skill: Skill) -> Option<usize> {
+        self.skills.get(&skill).cloned()
+    }
+
+    pub fn has_tool(&self, tool: Tool) -> bool {
+        self.inventory.iter().any(|i| match i {
+                                      &Item::Tool(t, ..) |
+                                      &Item::Vessel(t, _) => {
+                                          t == tool
+                                      }
+                                      _ => false,
+                                  })
+    }
+
+    /// Tests if the dwarf has been trained in the kind of work an
+    /// order needs. Anyone can be told to go somewhere.
+    fn knows(&self, order: &Order) -> bool {
+        match order {
+            &Order::Mine(_) => self.skill(Skill::Mining).is_some(),
+            &Order::BuildWall(_) |
+            &Order::BuildFence(_) |
+            &Order::BuildRamp(_) |
+            &Order::BuildStairs(_) |
+            &Order::Destroy(_) => {
+                self.skill(Skill::Masonry).is_some() ||
+                    self.skill(Skill::Carpentry).is_some()
+            }
+            &Order::FellTrees(_) => {
+                self.skill(Skill::Woodcutting).is_some()
+            }
+            &Order::GatherPlants(_) => {
+                self.skill(Skill::Herbalism).is_some()
+            }
+            &Order::CartGoods(..) => self.skill(Skill::Hauling).is_some(),
+            &Order::Go(_) => true,
+        }
+    }
+
+    /// The skill needed to work a material: stone needs masonry, and
+    /// wood needs carpentry.
+    fn trade(material: Material) -> Skill {
+        match material {
+            Material::Stone(_) => Skill::Masonry,
+            Material::Wood(_) => Skill::Carpentry,
+        }
+    }
+
+    /// Tests if the dwarf knows how to build with a material.
+    fn uses(&self, material: Material) -> bool {
+        self.skill(Dwarf::trade(material)).is_some()
+    }
+
+    /// The place in the inventory of a material the dwarf can build
+    /// with, if it carries any.
+    fn material(&self) -> Option<usize> {
+        self.inventory.iter().position(|i| match i {
+                                           &Item::Material(m) => {
+                                               self.uses(m)
+                                           }
+                                           _ => false,
+                                       })
+    }
+
+    /// The structure a build order puts up.
+    fn structure(order: Order) -> Option<Structure> {
+        match order {
+            Order::BuildWall(_) => Some(Structure::Wall),
+            Order::BuildFence(_) => Some(Structure::Fence),
+            Order::BuildRamp(_) => Some(Structure::Ramp),
+            Order::BuildStairs(_) => Some(Structure::Stairs),
+            _ => None,
+        }
+    }
+
+    fn distance_to(&self, (x, y, z): Point3D) -> isize {
+        let (dx, dy, dz) = (x as isize - self.pos.0 as isize,
+                            y as isize - self.pos.1 as isize,
+                            z as isize - self.pos.2 as isize);
+        dx * dx + dy * dy + dz * dz
+    }
+
+    fn is_food(tile: Tile) -> bool {
+        match tile {
+            Tile::Item(Item::Food(Food::Herb(..))) |
+            Tile::Item(Item::Food(Food::Meat(..))) => true,
+            _ => false,
+        }
+    }
+
+    fn is_edible(tile: Tile) -> bool {
+        match tile {
+            Tile::Vegetation(VegType::Dandelion, ..) |
+            Tile::Vegetation(VegType::Chickweed, ..) |
+            Tile::Vegetation(VegType::Crabapple, ..) => true,
+            _ => false,
+        }
+    }
+
+    /// Finds the closest point in sight (and within a couple of levels
+    /// of the dwarf) holding a tile that passes the test.
+    fn find_nearest<F>(&self, map: &World, test: F) -> Option<Point3D>
+        where F: Fn(Tile) -> bool
+    {
+        let r = self.species.sight as isize;
+        let (x, y, z) = (self.pos.0 as isize,
+                         self.pos.1 as isize,
+                         self.pos.2 as isize);
+        (-r..r + 1)
+            .flat_map(|dy| (-r..r + 1).map(move |dx| (dx, dy)))
+            .flat_map(|(dx, dy)| (-2..3).map(move |dz| (dx, dy, dz)))
+            .filter(|&(dx, dy, dz)| {
+                x + dx >= 0 && y + dy >= 0 && z + dz >= 0
+            })
+            .map(|(dx, dy, dz)| {
+                ((x + dx) as usize, (y + dy) as usize, (z + dz) as usize)
+            })
+            .filter(|pnt| map.tile_at(*pnt).map_or(false, |t| test(t)))
+            .min_by_key(|&(px, py, pz)| {
+                let (dx, dy, dz) = (px as isize - x,
+                                    py as isize - y,
+                                    pz as isize - z);
+                dx * dx + dy * dy + dz * dz
+            })
+    }
+
+    /// Tests if a tile is water too deep for a dwarf to wade through.
+    fn too_deep(tile: Tile) -> bool {
+        match tile {
+            Tile::Water(_, _, depth) => depth > WADE_DEPTH,
+            _ => false,
+        }
+    }
+
+    fn create_path_to(&self,
+                      map: &World,
+                      goal: Point3D)
+        -> Option<Vec<Point3D>> {
+        find_path(map,
+                  self.pos,
+                  goal,
+                  Box::new(|point| {
+            let dry = map.unit_at((point.0, point.1))
+                         .and_then(|u| u.biome)
+                         .map_or(false, |b| {
+                b.biome_type != BiomeType::Water
+            });
+            dry && !map.tile_at(point).map_or(false, Dwarf::too_deep)
+        }))
+    }
+
+    /// Sets the path to the given point, or gives up on the current
+    /// goal if there is no way there.
+    fn go_to(&mut self, map: &World, pnt: Point3D) {
+        if pnt == self.pos {
+            self.arrived = true;
+        } else if let Some(mut path) = self.create_path_to(map, pnt) {
+            path.reverse();
+            self.path = Some(path);
+        } else {
+            self.fail_goal();
+        }
+    }
+
+    /// Picks a spot to work on the target from, in one of the columns
+    /// around it: in an opening next to it, or on top of a column up
+    /// to a level higher. Except when building, the dwarf can also
+    /// work from on top of the target. The closest spot the dwarf can
+    /// get to is picked.
+    fn work_site(&self,
+                 map: &World,
+                 order: Order,
+                 (x, y, z): Point3D)
+        -> Option<Point3D> {
+        let pos = self.pos;
+        let tops = strict_adjacent((x, y))
+            .into_iter()
+            .filter_map(|p| {
+                map.unit_at(p).map(|unit| {
+                    let tiles = unit.tiles.borrow();
+                    let top = tiles.iter()
+                                   .rposition(|t| t.solid())
+                                   .map_or(0, |z| z + 1);
+                    (p.0, p.1, top)
+                })
+            });
+        let mut beside = strict_3d_adjacent((x, y, z + 1), map)
+            .into_iter()
+            .chain(tops)
+            .filter(|&(px, py, pz)| {
+                (px, py) != (x, y) && pz + 1 >= z && pz <= z + 2 &&
+                    map.tile_at((px, py, pz))
+                       .map_or(true, |t| !Dwarf::too_deep(t))
+            })
+            .collect::<Vec<_>>();
+        beside.sort_by_key(|&(px, py, _)| {
+            let (dx, dy) = (px as isize - pos.0 as isize,
+                            py as isize - pos.1 as isize);
+            dx * dx + dy * dy
+        });
+        let on_top = match order {
+            Order::BuildWall(_) |
+            Order::BuildFence(_) |
+            Order::BuildRamp(_) |
+            Order::Destroy(_) => None,
+            _ => Some((x, y, map.location_z((x, y)))),
+        };
+        beside.into_iter()
+              .chain(on_top)
+              .find(|&site| self.create_path_to(map, site).is_some())
+    }
+
+    fn continue_movement(&mut self) {
+        let pos = self.pos;
+        let next = self.path
+                       .as_mut()
+                       .and_then(|path| {
+            path.retain(|p| *p != pos);
+            path.pop()
+        });
+        match next {
+            Some(npos) => self.pos = npos,
+            None => {
+                self.path = None;
+                self.arrived = true;
+            }
+        }
+    }
+
+    fn finish_goal(&mut self) {
+        self.path = None;
+        self.arrived = false;
+        self.target = None;
+        self.progress = 0;
+        self.current_goal = self.goals.pop();
+    }
+
+    /// Gives up on the current goal. The dwarf will not try the same
+    /// kind of goal again for a while.
+    fn fail_goal(&mut self) {
+        self.failed_goal = self.current_goal;
+        self.retry = RETRY_DELAY;
+        self.finish_goal();
+    }
+
+    /// Adds a goal for a need, unless it failed recently.
+    fn add_need(&mut self, mission: Mission) {
+        if self.retry == 0 || self.failed_goal != Some(mission) {
+            self.add_goal(mission);
+        }
+    }
+
+    /// Eats something from the inventory if there is anything to eat.
+    fn eat_ration(&mut self) -> bool {
+        let ration = self.inventory
+                         .iter()
+                         .position(|i| match i {
+                                       &Item::Food(Food::Herb(..)) |
+                                       &Item::Food(Food::Meat(..)) => {
+                                           true
+                                       }
+                                       _ => false,
+                                   });
+        if let Some(i) = ration {
+            self.inventory.remove(i);
+            self.hunger = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Drinks from whatever water the dwarf carries, until it is no
+    /// longer thirsty or has none left. Each mouthful is boiled
+    /// first, so dirty or murky water does no harm.
+    fn drink_carried(&mut self) -> bool {
+        let mut drank = false;
+        for item in self.inventory.iter_mut() {
+            if let &mut Item::Vessel(_, ref mut vessel) = item {
+                while self.thirst > 0 {
+                    match vessel.pour(1).map(Drinkable::boild) {
+                        Some(water) => {
+                            self.thirst -= water.satisfaction();
+                            self.sick += sickness(water.purity);
+                            drank = true;
+                        }
+                        None => break,
+                    }
+                }
+            }
+        }
+        self.thirst = cmp::max(self.thirst, 0);
+        drank
+    }
+
+    /// The water beside the dwarf and where it is, if there is any
+    /// there to drink.
+    fn water_nearby(&self,
+                    map: &World)
+        -> Option<(Point3D, Draught)> {
+        strict_3d_adjacent(self.pos, map)
+            .into_iter()
+            .filter_map(|p| {
+                            map.tile_at(p)
+                               .and_then(Draught::from_tile)
+                               .map(|water| (p, water))
+                        })
+            .next()
+    }
+
+    /// Fills every vessel the dwarf carries from the water next to
+    /// it, straining out the worst of any dirt as it goes. There is
+    /// only as much to go round as the tile holds, and whatever is
+    /// taken has to be drawn off the map.
+    fn fill_vessels(&mut self, map: &World) -> MissionResult {
+        let (pnt, water) = match self.water_nearby(map) {
+            Some(found) => found,
+            None => return MissionResult::NoResult,
+        };
+        let mut source = Container::new(water.depth);
+        source.fill(water);
+        for item in self.inventory.iter_mut() {
+            if let &mut Item::Vessel(_, ref mut vessel) = item {
+                if let Some(drawn) = source.pour(vessel.room()) {
+                    vessel.fill(drawn.filter());
+                }
+            }
+        }
+        match water.depth - source.contents() {
+            0 => MissionResult::NoResult,
+            taken => MissionResult::DrawWater(pnt, taken),
+        }
+    }
+
+    /// Works out where to go to satisfy the current goal.
+    fn set_up_goal(&mut self, map: &World, mission: Mission) {
+        match mission {
+            Mission::Eat(_) => {
+                if self.eat_ration() {
+                    self.finish_goal();
+                } else {
+                    let food = self.find_nearest(map, |t| {
+                        Dwarf::is_food(t) || Dwarf::is_edible(t)
+                    })
+                                   .or_else(|| {
+                        self.find_stocked(map, |i| {
+                            Dwarf::is_food(Tile::Item(*i))
+                        })
+                    });
+                    match food {
+                        Some(pnt) => self.go_to(map, pnt),
+                        None => self.fail_goal(),
+                    }
+                }
+            }
+            Mission::Drink(_) if self.drink_carried() => {
+                self.finish_goal();
+            }
+            Mission::Drink(_) => {
+                // Water that does no harm is worth going further for.
+                let safe = |t| match t {
+                    Tile::Water(p, State::Liquid, _) => {
+                        sickness(p) == 0
+                    }
+                    _ => false,
+                };
+                let water = self.find_nearest(map, safe).or_else(|| {
+                    self.find_nearest(map, |t| {
+                        matches!(t, Tile::Water(_, State::Liquid, _))
+                    })
+                });
+                let shore = water.and_then(|pnt| {
+                    strict_3d_adjacent(pnt, map)
+                        .into_iter()
+                        .find(|p| {
+                            map.tile_at(*p)
+                               .map_or(false, |t| !World::is_water(t))
+                        })
+                });
+                match shore {
+                    Some(pnt) => self.go_to(map, pnt),
+                    None => self.fail_goal(),
+                }
+            }
+            Mission::Sleep(_) => self.arrived = true,
+            Mission::Go(point, _) => {
+                let z = map.location_z_from_to(self.pos.2, point);
+                self.go_to(map, (point.0, point.1, z));
+            }
+            Mission::Obey(_, Order::Go((x, y, _))) => {
+                let z = map.location_z_from_to(self.pos.2, (x, y));
+                self.go_to(map, (x, y, z));
+            }
+            Mission::Obey(_, order) => {
+                if Dwarf::structure(order).is_some() &&
+                    self.material().is_none()
+                {
+                    self.fetch_material(map);
+                } else if matches!(order, Order::CartGoods(..)) &&
+                           self.carrying.is_none()
+                {
+                    self.fetch_goods(map, order);
+                } else if let Some(pnt) = jobs::target(&order, map) {
+                    if self.target != Some(pnt) {
+                        self.progress = 0;
+                    }
+                    self.target = Some(pnt);
+                    match self.work_site(map, order, pnt) {
+                        Some(site) => self.go_to(map, site),
+                        None => self.fail_goal(),
+                    }
+                } else {
+                    self.finish_goal();
+                }
+            }
+            _ => self.fail_goal(),
+        }
+    }
+
+    /// Does one step of work on a player order at the job site.
+    fn work(&mut self,
+            map: &World,
+            order: Order,
+            rng: &mut Stream)
+        -> MissionResult {
+        match order {
+            Order::Go(_) => {
+                self.finish_goal();
+                MissionResult::NoResult
+            }
+            Order::Mine(_) => self.mine(map),
+            Order::BuildWall(_) |
+            Order::BuildFence(_) |
+            Order::BuildRamp(_) |
+            Order::BuildStairs(_) => self.build(map, order),
+            Order::FellTrees(_) |
+            Order::GatherPlants(_) => self.harvest(map, order),
+            Order::CartGoods(..) => self.haul(map, order),
+            Order::Destroy(_) => self.deconstruct(map, order, rng),
+            _ => {
+                self.fail_goal();
+                MissionResult::NoResult
+            }
+        }
+    }
+
+    /// Digs away at the target stone, until enough work has gone into
+    /// it to break it loose. Harder stone takes longer, and so does
+    /// digging without a pickaxe.
+    fn mine(&mut self, map: &World) -> MissionResult {
+        let target = self.target;
+        match target.and_then(|pnt| map.tile_at(pnt)) {
+            Some(Tile::Stone(kind, State::Solid)) => {
+                let tool = if self.has_tool(Tool::Pickaxe) {
+                    1
+                } else {
+                    NO_TOOL_PENALTY
+                };
+                let skill = self.skill(Skill::Mining).unwrap_or(0);
+                let needed = DIG_TIME * kind.hardness() * tool /
+                    (skill + 1);
+                self.progress += 1;
+                if self.progress >= needed {
+                    self.finish_goal();
+                    MissionResult::Dig(target.unwrap())
+                } else {
+                    MissionResult::NoResult
+                }
+            }
+            _ => {
+                self.finish_goal();
+                MissionResult::NoResult
+            }
+        }
+    }
+
+    /// Finds the closest stocked item that passes the test.
+    fn find_stocked<F>(&self, map: &World, test: F) -> Option<Point3D>
+        where F: Fn(&Item) -> bool
+    {
+        map.stocked(test)
+           .into_iter()
+           .map(|(pnt, _)| pnt)
+           .min_by_key(|pnt| self.distance_to(*pnt))
+    }
+
+    /// Heads for the closest building material the dwarf can use,
+    /// looking in the stockpiles first.
+    fn fetch_material(&mut self, map: &World) {
+        let found = self.find_stocked(map, |i| match i {
+                                          &Item::Material(m) => {
+                                              self.uses(m)
+                                          }
+                                          _ => false,
+                                      })
+                        .or_else(|| {
+            self.find_nearest(map, |t| match t {
+                Tile::Item(Item::Material(m)) => self.uses(m),
+                _ => false,
+            })
+        });
+        match found {
+            Some(pnt) => self.go_to(map, pnt),
+            None => self.fail_goal(),
+        }
+    }
+
+    /// Heads for the closest loose item that the stockpile the order
+    /// is for will take.
+    fn fetch_goods(&mut self, map: &World, order: Order) {
+        let found = map.stockpile_at(order.area().0).and_then(|pile| {
+            map.loose_items()
+               .into_iter()
+               .filter(|&(_, ref item)| pile.accepts(item))
+               .map(|(pnt, _)| pnt)
+               .min_by_key(|pnt| self.distance_to(*pnt))
+        });
+        match found {
+            Some(pnt) => self.go_to(map, pnt),
+            None => self.fail_goal(),
+        }
+    }
+
+    /// Picks up the goods the dwarf is standing on, or puts down the
+    /// goods it carries once it is at the stockpile.
+    fn haul(&mut self, map: &World, order: Order) -> MissionResult {
+        let target = self.target;
+        match (self.carrying, target) {
+            (None, _) => {
+                let pile = map.stockpile_at(order.area().0);
+                match (map.tile_at(self.pos), pile) {
+                    (Some(Tile::Item(item)), Some(pile)) if
+                        pile.accepts(&item) => {
+                        self.carrying = Some(item);
+                        // Head for the stockpile next.
+                        self.path = None;
+                        self.arrived = false;
+                        MissionResult::RemoveItem(self.pos)
+                    }
+                    _ => {
+                        self.fail_goal();
+                        MissionResult::NoResult
+                    }
+                }
+            }
+            (Some(item), Some(pnt)) if jobs::target(&order, map) ==
+                                           Some(pnt) => {
+                self.carrying = None;
+                self.finish_goal();
+                MissionResult::ReplaceItem(pnt, item)
+            }
+            _ => {
+                self.finish_goal();
+                MissionResult::NoResult
+            }
+        }
+    }
+
+    /// Tests if the dwarf is still on a hauling job for a stockpile
+    /// that takes the item.
+    fn hauling(&self, map: &World, item: &Item) -> bool {
+        self.orders().into_iter().any(|order| match order {
+            Order::CartGoods(..) => {
+                map.stockpile_at(order.area().0)
+                   .map_or(false, |pile| pile.accepts(item))
+            }
+            _ => false,
+        })
+    }
+
+    /// Picks up the building material the dwarf is standing on.
+    fn pick_up_material(&mut self, map: &World) -> MissionResult {
+        match map.tile_at(self.pos) {
+            Some(Tile::Item(Item::Material(m))) if self.uses(m) => {
+                self.inventory.push(Item::Material(m));
+                // Head for the building site next.
+                self.path = None;
+                self.arrived = false;
+                MissionResult::RemoveItem(self.pos)
+            }
+            _ => {
+                self.fail_goal();
+                MissionResult::NoResult
+            }
+        }
+    }
+
+    /// Works on a structure once the dwarf has the material for it,
+    /// using the material up when the structure is finished.
+    fn build(&mut self, map: &World, order: Order) -> MissionResult {
+        let target = self.target;
+        match (self.material(), target) {
+            (None, _) => self.pick_up_material(map),
+            (Some(i), Some(pnt)) if jobs::target(&order, map) ==
+                                        Some(pnt) => {
+                let skill = match self.inventory[i] {
+                    Item::Material(m) => Dwarf::trade(m),
+                    _ => Skill::Masonry,
+                };
+                let needed = BUILD_TIME /
+                    (self.skill(skill).unwrap_or(0) + 1);
+                self.progress += 1;
+                if self.progress >= needed {
+                    let material = match self.inventory.remove(i) {
+                        Item::Material(m) => m,
+                        _ => unreachable!(),
+                    };
+                    let structure = Dwarf::structure(order).unwrap();
+                    self.finish_goal();
+                    MissionResult::Build(pnt,
+                                         Tile::Construction(material,
+                                                            structure))
+                } else {
+                    MissionResult::NoResult
+                }
+            }
+            _ => {
+                self.finish_goal();
+                MissionResult::NoResult
+            }
+        }
+    }
+
+    /// Takes down the target structure. The material it was made of
+    /// may be salvaged, more often the more skilled the builder is,
+    /// or else it breaks.
+    fn deconstruct(&mut self,
+                   map: &World,
+                   order: Order,
+                   rng: &mut Stream)
+        -> MissionResult {
+        let target = self.target;
+        let tile = target.and_then(|pnt| map.tile_at(pnt));
+        match (target, tile) {
+            (Some(pnt), Some(Tile::Construction(material, _))) if
+                jobs::target(&order, map) == Some(pnt) => {
+                let level = self.skill(Dwarf::trade(material))
+                                .unwrap_or(0);
+                self.progress += 1;
+                if self.progress >= DESTROY_TIME / (level + 1) {
+                    self.finish_goal();
+                    let chance = SALVAGE_CHANCE * level as f32;
+                    if rng.gen::<f32>() < chance {
+                        MissionResult::ReplaceItem(pnt,
+                                                   Item::Material(material))
+                    } else {
+                        MissionResult::RemoveItem(pnt)
+                    }
+                } else {
+                    MissionResult::NoResult
+                }
+            }
+            _ => {
+                self.finish_goal();
+                MissionResult::NoResult
+            }
+        }
+    }
+
+    /// Cuts the target tree down into a log, or picks the target plant.
+    /// Taller trees take longer to fell.
+    fn harvest(&mut self, map: &World, order: Order) -> MissionResult {
+        let target = self.target;
+        let tile = target.and_then(|pnt| map.tile_at(pnt));
+        match (target, tile) {
+            (Some(pnt), Some(Tile::Vegetation(vt, height, _))) if
+                jobs::target(&order, map) == Some(pnt) => {
+                let (skill, time, item) = match order {
+                    Order::FellTrees(_) => {
+                        (Skill::Woodcutting,
+                         FELL_TIME * height as usize,
+                         Item::Material(Material::Wood(vt)))
+                    }
+                    _ => {
+                        (Skill::Herbalism,
+                         GATHER_TIME,
+                         Item::Food(Food::Herb(vt)))
+                    }
+                };
+                let needed = time / (self.skill(skill).unwrap_or(0) + 1);
+                self.progress += 1;
+                if self.progress >= needed {
+                    self.finish_goal();
+                    MissionResult::ReplaceItem(pnt, item)
+                } else {
+                    MissionResult::NoResult
+                }
+            }
+            _ => {
+                self.finish_goal();
+                MissionResult::NoResult
+            }
+        }
+    }
+
+    /// Carries out the current goal once the dwarf is where it needs
+    /// to be.
+    fn stationary_action(&mut self,
+                         map: &World,
+                         mission: Mission,
+                         rng: &mut Stream)
+        -> MissionResult {
+        match mission {
+            Mission::Eat(_) => {
+                let tile = map.tile_at(self.pos).unwrap_or(Tile::Empty);
+                if Dwarf::is_food(tile) {
+                    self.hunger = 0;
+                    self.finish_goal();
+                    MissionResult::RemoveItem(self.pos)
+                } else if Dwarf::is_edible(tile) {
+                    self.hunger /= 2;
+                    self.finish_goal();
+                    MissionResult::RemoveItem(self.pos)
+                } else {
+                    self.fail_goal();
+                    MissionResult::NoResult
+                }
+            }
+            Mission::Drink(_) => {
+                if let Some((_, water)) = self.water_nearby(map) {
+                    self.sicken(water.purity);
+                }
+                self.thirst = 0;
+                let drawn = self.fill_vessels(map);
+                self.finish_goal();
+                drawn
+            }
+            Mission::Sleep(_) => {
+                self.sleep -= REST_RATE;
+                if self.sleep <= 0 {
+                    self.sleep = 0;
+                    self.finish_goal();
+                }
+                MissionResult::NoResult
+            }
+            Mission::Obey(_, order) => self.work(map, order, rng),
+            _ => {
+                self.finish_goal();
+                MissionResult::NoResult
+            }
+        }
+    }
+}
+
+impl Living for Dwarf {
+    fn add_goal(&mut self, mission: Mission) {
+        if matches!(mission, Mission::Die) {
+            self.failed_goal = None;
+            self.path = None;
+            self.arrived = false;
+            self.current_goal = Some(Mission::Die);
+            self.species.health = 0;
+            self.goals = vec![];
+            return;
+        }
+        let current = self.current_goal;
+        let pending = current == Some(mission) ||
+            self.goals.contains(&mission);
+        if !pending {
+            match current {
+                // More urgent goals interrupt whatever the dwarf is
+                // doing, which will be picked up again later.
+                Some(c) if mission.cmp(&c) == Ordering::Greater => {
+                    self.goals.push(c);
+                    self.path = None;
+                    self.arrived = false;
+                    self.current_goal = Some(mission);
+                }
+                Some(_) => self.goals.push(mission),
+                None => self.current_goal = Some(mission),
+            }
+            self.goals.sort();
+        }
+    }
+
+    fn remove_goal(&mut self, tag: &Mission) -> Option<Mission> {
+        if self.current_goal.as_ref() == Some(tag) {
+            let old = self.current_goal;
+            self.finish_goal();
+            old
+        } else {
+            self.goals.remove_item(tag)
+        }
+    }
+
+    fn prioritize(&mut self, n: usize) -> Vec<Mission> {
+        if n <= self.goals.len() {
+            self.goals.drain(0..n).collect()
+        } else {
+            self.goals.clone()
+        }
+    }
+
+    fn execute_mission(&mut self,
+                       map: &World,
+                       rng: &mut Stream)
+        -> MissionResult {
+        self.hunger += 1;
+        self.thirst += 2;
+        self.sleep += 1;
+        self.retry = self.retry.saturating_sub(1);
+        self.suffer();
+        // The ground might have been dug out from under the dwarf.
+        if map.tile_at(self.pos).map_or(false, |t| t.solid()) {
+            let (x, y, z) = self.pos;
+            self.pos = (x, y, map.location_z_from_to(z, (x, y)));
+        }
+        // Goods are put down once the hauling job is over, whether it
+        // was done, given up or called off.
+        if self.carrying.map_or(false, |i| !self.hauling(map, &i)) {
+            let item = self.carrying.take().unwrap();
+            return MissionResult::PutDown(self.pos, item);
+        }
+        self.auto_add_mission(map, vec![], rng);
+
+        let goal = self.current_goal;
+        match goal {
+            None => MissionResult::NoResult,
+            Some(Mission::Die) => MissionResult::Die,
+            Some(mission) => {
+                if self.path.is_some() {
+                    self.continue_movement();
+                    MissionResult::NoResult
+                } else if self.arrived {
+                    self.stationary_action(map, mission, rng)
+                } else {
+                    self.set_up_goal(map, mission);
+                    MissionResult::NoResult
+                }
+            }
+        }
+    }
+
+    fn auto_add_mission(&mut self,
+                        _map: &World,
+                        _in_sight: Vec<(Tile, Point3D)>,
+                        _rng: &mut Stream)
+        -> Option<Mission> {
+        if self.thirst >= THIRST_THRESHOLD ||
+            self.hunger >= HUNGER_THRESHOLD ||
+            self.sleep >= SLEEP_THRESHOLD
+        {
+            self.add_goal(Mission::Die);
+            return Some(Mission::Die);
+        }
+        if self.thirst >= THIRST_THRESHOLD - TOLERANCE {
+            self.add_need(Mission::Drink(DRINK_PRIORITY));
+        }
+        if self.hunger >= HUNGER_THRESHOLD - TOLERANCE {
+            self.add_need(Mission::Eat(EAT_PRIORITY));
+        }
+        if self.sleep >= SLEEP_THRESHOLD - TOLERANCE {
+            self.add_need(Mission::Sleep(SLEEP_PRIORITY));
+        }
+        self.current_goal
+    }
+
+    fn orders(&self) -> Vec<Order> {
+        self.current_goal
+            .iter()
+            .chain(self.goals.iter())
+            .filter_map(|m| match m {
+                            &Mission::Obey(_, order) => Some(order),
+                            _ => None,
+                        })
+            .collect()
+    }
+
+    fn can_obey(&self, order: &Order) -> bool {
+        self.species.health > 0 && self.orders().is_empty() &&
+            self.knows(order)
+    }
+
+    fn drop_carried(&mut self) -> Option<Item> {
+        self.carrying.take()
+    }
+
+    fn hurt(&mut self, damage: i32) {
+        self.species.health -= damage;
+        if self.species.health <= 0 {
+            self.add_goal(Mission::Die);
+        }
+    }
+
+    fn sick(&self) -> usize { self.sick }
+    fn sick_mut(&mut self) -> &mut usize { &mut self.sick }
+
+    fn current_pos(&self) -> Point3D { self.pos }
+    fn current_goal(&self) -> Option<Mission> { self.current_goal }
+    fn species(&self) -> &SpeciesProperties { &self.species }
+    fn snapshot(&self) -> Creature {
+        Creature::Dwarf(self.clone())
+    }
+    fn goals(&self) -> (Option<&Mission>, Option<&Mission>) {
+        (self.current_goal.as_ref(), self.failed_goal.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use worldgen::terrain::{IgneousRocks, StoneTypes, WATER_BIOME};
+
+    fn rock(height: usize) -> Vec<Tile> {
+        let basalt = StoneTypes::Igneous(IgneousRocks::Basalt);
+        vec![Tile::Stone(basalt, State::Solid); height]
+    }
+
+    #[test]
+    fn mines_from_a_ledge_above_the_target() {
+        let mut map = World::from_columns(vec![vec![rock(4); 3],
+                                               vec![rock(4),
+                                                    rock(3),
+                                                    rock(4)],
+                                               vec![rock(4); 3]]);
+        // The target is on the shore, so the only dry ground to work
+        // from is the ledge around it.
+        map.map[1][1].biome = Some(WATER_BIOME);
+        let skills = vec![(Skill::Mining, 3)];
+        let dwarf = Dwarf::fresh((0, 0, 4), "Urist", skills, vec![]);
+        let order = Order::Mine(((1, 1), (1, 1)));
+        assert_eq!(dwarf.work_site(&map, order, (1, 1, 2)),
+                   Some((0, 1, 4)));
+    }
+
+    /// Takes down a hundred walls with a dwarf of the given masonry
+    /// skill, and counts how many were salvaged.
+    fn salvaged(skills: Vec<(Skill, usize)>) -> usize {
+        let basalt = StoneTypes::Igneous(IgneousRocks::Basalt);
+        let mut column = rock(2);
+        column.push(Tile::Construction(Material::Stone(basalt),
+                                       Structure::Wall));
+        let map = World::from_columns(vec![vec![column]]);
+        let order = Order::Destroy(((0, 0), (0, 0)));
+        let mut rng = Stream::new(42, 0);
+        let mut dwarf = Dwarf::fresh((0, 0, 2), "Urist", skills,
+                                     vec![]);
+        let mut count = 0;
+        for _ in 0..100 {
+            let result = loop {
+                dwarf.target = Some((0, 0, 2));
+                match dwarf.deconstruct(&map, order, &mut rng) {
+                    MissionResult::NoResult => {}
+                    result => break result,
+                }
+            };
+            if let MissionResult::ReplaceItem(..) = result {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn salvages_more_the_more_skilled() {
+        let unskilled = salvaged(vec![]);
+        let novice = salvaged(vec![(Skill::Masonry, 1)]);
+        let master = salvaged(vec![(Skill::Masonry, 3)]);
+        assert_eq!(unskilled, 0);
+        assert!(0 < novice && novice < master && master < 100);
+    }
+}
@@ -1,11 +1,11 @@
-use draw::DrawChar;
+use draw::{Backend, BackgroundFlag, DrawChar};
+use random::Stream;
 use std::option::Option;
 
-use tcod::{BackgroundFlag, Console, RootConsole};
-
 use utils::{Point2D, Point3D, Rect2D, Rect2D3D};
 use worldgen::World;
-use worldgen::terrain::{Item, Tile};
+use worldgen::terrain::{Depth, Item, ItemCategory, LiquidPurity,
+                        Tile};
 
 pub mod animal;
 pub mod bird;
@@ -16,8 +16,22 @@ pub type Priority = usize;
 
 pub type HealthLevel = usize;
 
+/// How many ticks of sickness it takes to lose a point of health.
+pub const SICK_RATE: usize = 10;
+
+/// How many ticks drinking water of a given purity leaves an actor
+/// sick for. Muddy water lays it low for a while, and toxic water
+/// for long enough to kill most things.
+pub fn sickness(purity: LiquidPurity) -> usize {
+    match purity {
+        LiquidPurity::Muddy => 300,
+        LiquidPurity::Toxic => 3000,
+        _ => 0,
+    }
+}
+
 /// The mental mood of a living actor.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum Mood {
     Angry,
     Fearful,
@@ -32,18 +46,52 @@ pub enum Mood {
 }
 
 /// Player assigned missions (orders)
-#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Copy)]
+#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Copy,
+         Serialize, Deserialize)]
 pub enum Order {
     GatherPlants(Rect2D),
     FellTrees(Rect2D),
 
-    CartGoods(Rect2D),
+    CartGoods(Rect2D, Option<ItemCategory>),
     Go(Point3D),
 
     Mine(Rect2D),
     BuildWall(Rect2D),
     BuildFence(Rect2D),
     BuildRamp(Point2D),
+    BuildStairs(Point2D),
+    Destroy(Rect2D),
+}
+
+impl Order {
+    /// The area of the map the order covers.
+    pub fn area(&self) -> Rect2D {
+        use self::Order::*;
+        match self {
+            &GatherPlants(r) | &FellTrees(r) | &CartGoods(r, _) |
+            &Mine(r) | &BuildWall(r) | &BuildFence(r) |
+            &Destroy(r) => r,
+            &Go((x, y, _)) => ((x, y), (x, y)),
+            &BuildRamp(p) | &BuildStairs(p) => (p, p),
+        }
+    }
+
+    /// The same order, narrowed down to a single tile.
+    pub fn at(&self, pnt: Point2D) -> Order {
+        use self::Order::*;
+        match self {
+            &GatherPlants(_) => GatherPlants((pnt, pnt)),
+            &FellTrees(_) => FellTrees((pnt, pnt)),
+            &CartGoods(_, f) => CartGoods((pnt, pnt), f),
+            &Mine(_) => Mine((pnt, pnt)),
+            &BuildWall(_) => BuildWall((pnt, pnt)),
+            &BuildFence(_) => BuildFence((pnt, pnt)),
+            &BuildRamp(_) => BuildRamp(pnt),
+            &BuildStairs(_) => BuildStairs(pnt),
+            &Destroy(_) => Destroy((pnt, pnt)),
+            &Go(p) => Go(p),
+        }
+    }
 }
 
 /// Actions that can be performed on an eatable object.
@@ -55,7 +103,8 @@ pub trait Eatable {
 pub trait Drinkable {
     fn filter(self) -> Self;
     fn boild(self) -> Self;
-    fn satisfaction(self) -> Self;
+    /// How much thirst drinking it all quenches.
+    fn satisfaction(&self) -> i32;
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -65,18 +114,27 @@ pub enum MissionResult {
     Kill(usize),
     ReplaceItem(Point3D, Item),
     RemoveItem(Point3D),
+    PutDown(Point3D, Item),
+    /// Took the given depth of water out of the tile at a point.
+    DrawWater(Point3D, Depth),
+    Dig(Point3D),
+    Build(Point3D, Tile),
 }
 
 /// Basic missions that animals can assign to themselves
-#[derive(Debug, Eq, PartialOrd, Clone, Copy)]
+#[derive(Debug, Eq, PartialOrd, Clone, Copy, Serialize,
+         Deserialize)]
 pub enum Mission {
     Eat(Priority),
     PickFood(Priority),
     Drink(Priority),
+    Sleep(Priority),
     AttackEnemy(Priority),
     GoToArea(Rect2D3D, Priority),
     Go(Point2D, Priority),
     Obey(Priority, Order),
+    /// Run from danger at the given point, such as a fire.
+    Flee(Point2D, Priority),
     Die,
 }
 
@@ -85,16 +143,16 @@ impl Ord for Mission {
     fn cmp(&self, other: &Self) -> Ordering {
         use self::Mission::*;
         let priority_a = match self {
-            &PickFood(p) | &Eat(p) | &Drink(p) |
+            &PickFood(p) | &Eat(p) | &Drink(p) | &Sleep(p) |
             &AttackEnemy(p) | &GoToArea(_, p) | &Obey(p, _) => p,
-            &Go(_, p) => p,
+            &Go(_, p) | &Flee(_, p) => p,
             &Die => 1000,
         };
 
         let priority_b = match other {
-            &PickFood(p) | &Eat(p) | &Drink(p) |
+            &PickFood(p) | &Eat(p) | &Drink(p) | &Sleep(p) |
             &AttackEnemy(p) | &GoToArea(_, p) | &Obey(p, _) => p,
-            &Go(_, p) => p,
+            &Go(_, p) | &Flee(_, p) => p,
             &Die => 1000,
         };
 
@@ -108,9 +166,11 @@ impl PartialEq for Mission {
         return match (self, other) {
             (&Eat(..), &Eat(..)) => true,
             (&Drink(..), &Drink(..)) => true,
+            (&Sleep(..), &Sleep(..)) => true,
             (&AttackEnemy(..), &AttackEnemy(..)) => true,
             (&GoToArea(..), &GoToArea(..)) => true,
             (&Obey(..), &Obey(..)) => true,
+            (&Flee(..), &Flee(..)) => true,
             _ => false,
         };
     }
@@ -127,21 +187,82 @@ pub trait Living {
     /// Returns the current goal.
     fn goals(&self) -> (Option<&Mission>, Option<&Mission>);
     /// Chooses highest priority mission, excecutes one step of it, and
-    /// returns it if done, otherwise returns None.
-    fn execute_mission(&mut self, ws: &World) -> MissionResult;
+    /// returns it if done, otherwise returns None. Any random choices
+    /// are drawn from `rng`.
+    fn execute_mission(&mut self,
+                       ws: &World,
+                       rng: &mut Stream)
+        -> MissionResult;
     /// Adds a mission when none is provided. Used all the time for
     /// animals. If there is already a mission going, returns None.
     fn auto_add_mission(&mut self,
                         ws: &World,
-                        adj: Vec<(Tile, Point3D)>)
+                        adj: Vec<(Tile, Point3D)>,
+                        rng: &mut Stream)
         -> Option<Mission>;
 
+    /// Returns the player orders the actor is working on. Only
+    /// settlers take orders, so for animals this is always empty.
+    fn orders(&self) -> Vec<Order> { vec![] }
+    /// Tests if the actor is able to, and free to, take on the order.
+    fn can_obey(&self, _order: &Order) -> bool { false }
+    /// Lets go of whatever the actor is carrying, if anything.
+    fn drop_carried(&mut self) -> Option<Item> { None }
+    /// Takes damage, and dies once its health has run out.
+    fn hurt(&mut self, damage: i32);
+    /// How many more ticks the actor will be sick for.
+    fn sick(&self) -> usize;
+    /// The ticks of sickness the actor has left, for `sicken` and
+    /// `suffer` to keep.
+    fn sick_mut(&mut self) -> &mut usize;
+    /// Makes the actor sick for a while, if the water it drank was
+    /// bad.
+    fn sicken(&mut self, purity: LiquidPurity) {
+        *self.sick_mut() += sickness(purity);
+    }
+    /// Lets a tick of sickness pass, losing a point of health every
+    /// `SICK_RATE` ticks of it.
+    fn suffer(&mut self) {
+        let hurt = {
+            let sick = self.sick_mut();
+            if *sick > 0 {
+                *sick -= 1;
+                *sick % SICK_RATE == 0
+            } else {
+                false
+            }
+        };
+        if hurt {
+            self.hurt(1);
+        }
+    }
+
     fn current_goal(&self) -> Option<Mission>;
     fn current_pos(&self) -> (usize, usize, usize);
     fn species(&self) -> &animal::SpeciesProperties;
+    /// Copies the actor out into a form that can be saved.
+    fn snapshot(&self) -> Creature;
+}
+/// A saved actor. Actors live behind trait objects in the world, so
+/// each kind gets a variant here to let the save file tell them
+/// apart again.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Creature {
+    Animal(animal::Animal),
+    Dwarf(dwarf::Dwarf),
 }
+
+impl Creature {
+    pub fn into_living(self) -> Box<Living> {
+        match self {
+            Creature::Animal(a) => Box::new(a),
+            Creature::Dwarf(d) => Box::new(d),
+        }
+    }
+}
+
 impl DrawChar for Living {
-    fn draw_char(&self, root: &mut RootConsole, pos: (usize, usize)) {
+    fn draw_char(&self, root: &mut Backend, pos: (usize, usize)) {
         root.put_char(pos.0 as i32,
                       pos.1 as i32,
                       self.species().chr,
@@ -1,63 +1,44 @@
-#![feature(box_syntax,
-           box_patterns,
-           vec_remove_item,
-           conservative_impl_trait,
-           exclusive_range_pattern,
-           const_fn)]
 #![allow(dead_code)]
 
 extern crate tcod;
-extern crate tcod_sys;
 #[macro_use]
-extern crate pipeline;
-
-#[macro_export]
-macro_rules! matches {
-    ($e:expr, $p:pat) => (
-        match $e {
-            $p => true,
-            _ => false
-        }
-    )
-}
+extern crate master_of_destiny;
 
-#[macro_export]
-macro_rules! get(
-    ($e:expr) => (match $e { Some(e) => e, None => return None })
-);
+use std::cmp;
 
 use tcod::{Color, FontLayout, FontType, Renderer, RootConsole};
 use tcod::console::{BackgroundFlag, Console, TextAlignment};
 use tcod::input;
 use tcod::input::KeyCode;
 
-mod utils;
-use utils::{Rect2D, clamp};
-
-mod life;
-
-mod draw;
-use draw::draw_map;
+use master_of_destiny::{life, save, time};
+use master_of_destiny::draw::{Describe, draw_map};
+use master_of_destiny::replay::{Input, REPLAY_PATH, Recorder};
+use master_of_destiny::utils::{Rect2D, clamp};
+use master_of_destiny::worldgen::{MAP_SIZE, World, WorldState};
+use master_of_destiny::worldgen::terrain::{BASE, ItemCategory,
+                                           TILES};
 
-mod physics;
+mod backend;
+use backend::TcodBackend;
 
 #[macro_use]
 mod ui;
 use ui::{Button, DrawUI, Layout, MouseUI, Textbox};
 
-mod worldgen;
-use worldgen::{World, WorldState};
-use worldgen::terrain::{BASE, TILES};
-
-mod time;
-
 const SHOW_FONT: &'static str = "assets/master20x20_ro.png";
 
 const SHOW_SIZE: (i32, i32) = (75, 50);
-const MAP_SIZE: (usize, usize) = (110, 70);
 
 const MOVE_DIST: i32 = 5;
 
+/// How many ticks are run each frame when skipping ahead to the next
+/// event.
+const SKIP_TICKS_PER_FRAME: usize = 200;
+
+/// How many of the latest events are kept to show the player.
+const MESSAGE_LOG: usize = 5;
+
 enum GameScreen {
     Menu,
     SelectCommand,
@@ -65,6 +46,7 @@ enum GameScreen {
     SelectBuildable,
     SelectDiggable,
     SelectOtherCommand,
+    SelectMoveCommand,
     Game,
     GetSeed,
     Loading,
@@ -77,6 +59,41 @@ struct Calc {
     pub screen_size: (i32, i32),
 }
 
+/// How fast the world runs on the game screen.
+#[derive(Copy, Clone, PartialEq)]
+enum Speed {
+    /// Stopped, with the map still showing so that orders can be
+    /// queued up.
+    Paused,
+    /// Some number of times the normal rate.
+    Times(usize),
+    /// As fast as possible, until something happens that is worth a
+    /// look.
+    UntilEvent,
+}
+
+impl Speed {
+    fn label(&self) -> String {
+        match *self {
+            Speed::Paused => "Paused".to_string(),
+            Speed::Times(n) => format!("{}x", n),
+            Speed::UntilEvent => "Skip".to_string(),
+        }
+    }
+
+    /// The speed after this one, for the speed button to cycle
+    /// through.
+    fn next(&self) -> Speed {
+        match *self {
+            Speed::Paused => Speed::Times(1),
+            Speed::Times(1) => Speed::Times(2),
+            Speed::Times(2) => Speed::Times(5),
+            Speed::Times(_) => Speed::UntilEvent,
+            Speed::UntilEvent => Speed::Paused,
+        }
+    }
+}
+
 enum PartialCommand {
     Move,
     Other,
@@ -100,8 +117,14 @@ struct Game {
     textbox: Textbox,
     selection: Rect2D,
     show_tools: Button,
+    speed_button: Button,
+    speed: Speed,
+    /// What has happened in the world lately, oldest first.
+    messages: Vec<String>,
     partial_command: PartialCommand,
     seed: u32,
+    recorder: Option<Recorder>,
+    ticker: time::Ticker,
     pub time: usize,
     pub screen: GameScreen,
     pub world_state: WorldState,
@@ -125,13 +148,20 @@ impl Game {
                                     (screen_size.0 - 7,
                                      screen_size.1 - 2),
                                     (6, 1)),
-            menu: Layout::new(vec!["New Game", "Use Seed", "Exit"],
+            speed_button: Button::new("1x",
+                                      (screen_size.0 - 16,
+                                       screen_size.1 - 2),
+                                      (8, 0)),
+            speed: Speed::Times(1),
+            messages: vec![],
+            menu: Layout::new(vec!["New Game", "Use Seed",
+                                   "Load Game", "Exit"],
                               (screen_size.0 / 2, 15),
-                              (8, 0),
-                              8),
+                              (10, 0),
+                              10),
 
             build_commands: Layout::new(vec!["Wall", "Fence",
-                                             "Ramp"],
+                                             "Ramp", "Stairs"],
                                         (screen_size.0 / 2, 15),
                                         (8, 0),
                                         8),
@@ -139,10 +169,14 @@ impl Game {
                                       (screen_size.0 / 2, 15),
                                       (8, 0),
                                       8),
-            move_commands: Layout::new(vec!["Go To", "Cart Goods"],
+            move_commands: Layout::new(vec!["Go To",
+                                            "Cart Goods",
+                                            "Cart Food",
+                                            "Cart Materials",
+                                            "Cart Tools"],
                                        (screen_size.0 / 2, 15),
-                                       (10, 0),
-                                       10),
+                                       (15, 0),
+                                       15),
             other_commands: Layout::new(vec!["Gather Plants",
                                              "Fell Trees"],
                                         (screen_size.0 / 2, 15),
@@ -157,6 +191,8 @@ impl Game {
                                       8),
             pause_menu: Layout::new(vec!["Main Menu",
                                          "Back",
+                                         "Save Game",
+                                         "Load Game",
                                          "Fullscreen",
                                          "Exit"],
                                     (screen_size.0 / 2, 4),
@@ -167,22 +203,126 @@ impl Game {
                                   (10, 0)),
             world_state: WorldState::new(),
             seed: 0,
+            recorder: None,
+            ticker: time::Ticker::new(time::TICKS_PER_SECOND),
         }
     }
 
     pub fn init_game(&mut self, seed: Option<u32>) {
-        let lpos = self.menu.buttons[self.menu.buttons.len() - 1]
-            .bbox
-            .0;
-        self.menu.buttons.insert(0,
-                                 Button::new("Resume",
-                                             (lpos.0 + 8 % 8,
-                                              lpos.1 + 1),
-                                             (8, 0)));
-        self.last_time = time::get_world_time();
-        self.seed = seed.unwrap_or(self.last_time as u32);
+        self.seed = seed.unwrap_or(time::get_world_time() as u32);
         let world = World::new(MAP_SIZE, self.seed);
+        self.world_state = WorldState::new();
         self.world_state.add_map(world);
+        self.messages.clear();
+        self.recorder =
+            Recorder::create(REPLAY_PATH, self.seed)
+                .map_err(|e| println!("Could not record replay: {}", e))
+                .ok();
+        self.start_game();
+    }
+
+    /// Gives an input to the world, adding it to the replay of the
+    /// game.
+    fn input(&mut self, input: Input) {
+        input.apply(&mut self.world_state);
+        let tick = self.world_state.tick;
+        if let Some(ref mut recorder) = self.recorder {
+            if let Err(e) = recorder.record(tick, input) {
+                println!("Could not record input: {}", e);
+            }
+        }
+    }
+
+    /// Replaces the running game, if there is one, with the saved
+    /// game.
+    fn load_game(&mut self) {
+        match save::load(save::SAVE_PATH) {
+            Ok(world_state) => {
+                // Replays start from a new world, so a loaded game
+                // is not recorded.
+                self.world_state = world_state;
+                self.recorder = None;
+                self.messages.clear();
+                if let Some(ref world) = self.world_state.map {
+                    self.seed = world.seed();
+                }
+                self.start_game();
+            }
+            Err(e) => println!("Could not load game: {}", e),
+        }
+    }
+
+    fn save_game(&self) {
+        match save::save(&self.world_state, save::SAVE_PATH) {
+            Ok(()) => println!("Saved game to {}", save::SAVE_PATH),
+            Err(e) => println!("Could not save game: {}", e),
+        }
+    }
+
+    /// Changes how fast the world runs. Stopping and starting it are
+    /// recorded in the replay.
+    fn set_speed(&mut self, speed: Speed) {
+        let (was, is) = (self.speed == Speed::Paused,
+                         speed == Speed::Paused);
+        if is && !was {
+            self.input(Input::Pause);
+        } else if was && !is {
+            self.input(Input::Resume);
+        }
+        self.speed = speed;
+        self.speed_button.text = format!("{:1$}", speed.label(), 8);
+    }
+
+    /// Steps the world as far as the current speed says it should
+    /// have come since the last frame. Skipping ahead pauses the game
+    /// at the first event.
+    fn run_world(&mut self) {
+        let ticks = match self.speed {
+            Speed::Paused => {
+                self.ticker.reset();
+                0
+            }
+            Speed::Times(n) => self.ticker.due() * n,
+            Speed::UntilEvent => {
+                self.ticker.reset();
+                SKIP_TICKS_PER_FRAME
+            }
+        };
+        for _ in 0..ticks {
+            let events = self.world_state.update();
+            for event in events.iter() {
+                self.log(event.describe());
+            }
+            if !events.is_empty() && self.speed == Speed::UntilEvent {
+                self.set_speed(Speed::Paused);
+                break;
+            }
+        }
+    }
+
+    /// Adds a message to the log shown in the HUD, forgetting the
+    /// oldest one once the log is full.
+    fn log(&mut self, message: String) {
+        self.messages.push(message);
+        if self.messages.len() > MESSAGE_LOG {
+            self.messages.remove(0);
+        }
+    }
+
+    /// Switches to the game screen once a world is in place, offering
+    /// to resume it from the main menu.
+    fn start_game(&mut self) {
+        if self.menu.buttons.iter().all(|b| b.id != "resume") {
+            let lpos = self.menu.buttons[self.menu.buttons.len() - 1]
+                .bbox
+                .0;
+            self.menu.buttons.insert(0,
+                                     Button::new("Resume",
+                                                 (lpos.0 + 8 % 8,
+                                                  lpos.1 + 1),
+                                                 (8, 0)));
+        }
+        self.last_time = time::get_world_time();
 
         self.constants.max_screen_move =
             (MAP_SIZE.0 as i32 - self.constants.screen_size.0 - 1,
@@ -193,16 +333,24 @@ impl Game {
     }
 
     pub fn draw(&mut self, root: &mut RootConsole) {
+        // The world only moves on the game screen; anywhere else, time
+        // stands still.
+        if !matches!(self.screen, GameScreen::Game) {
+            self.ticker.reset();
+        }
         match self.screen {
             GameScreen::SelectArea => {
                 root.clear();
-                draw_map(root,
+                draw_map(&mut TcodBackend(root),
                          &self.world_state,
+                         &self.messages,
                          false,
                          self.last_time);
                 let ((sx1, sy1), (sx2, sy2)) = self.selection;
-                for y in sy1..sy2 {
-                    for x in sx1..sx2 {
+                let (sx1, sx2) = (cmp::min(sx1, sx2), cmp::max(sx1, sx2));
+                let (sy1, sy2) = (cmp::min(sy1, sy2), cmp::max(sy1, sy2));
+                for y in sy1..sy2 + 1 {
+                    for x in sx1..sx2 + 1 {
                         root.set_char_foreground(x as i32,
                                                  y as i32,
                                                  Color::new(100,
@@ -226,6 +374,11 @@ impl Game {
                 self.other_commands
                     .draw(root, self.world_state.cursor);
             }
+            GameScreen::SelectMoveCommand => {
+                root.clear();
+                self.move_commands
+                    .draw(root, self.world_state.cursor);
+            }
 
             GameScreen::SelectCommand => {
                 root.clear();
@@ -287,17 +440,17 @@ impl Game {
                     .draw(root, self.world_state.cursor);
             }
             GameScreen::Game => {
-                let current_time = time::get_world_time();
-                self.world_state.update(current_time,
-                                        current_time -
-                                            self.last_time);
-                self.last_time = current_time;
-                draw_map(root,
+                self.run_world();
+                self.last_time = time::get_world_time();
+                draw_map(&mut TcodBackend(root),
                          &self.world_state,
+                         &self.messages,
                          self.show_hud,
                          self.last_time);
                 self.show_tools
                     .draw(root, self.world_state.cursor);
+                self.speed_button
+                    .draw(root, self.world_state.cursor);
             }
             GameScreen::Paused => {
                 root.clear();
@@ -329,14 +482,24 @@ impl Game {
         }
     }
 
+    /// The selected area in map coordinates, with the corners ordered
+    /// top left to bottom right.
+    fn selected_area(&self) -> Rect2D {
+        let ((x1, y1), (x2, y2)) = self.selection;
+        let (sx, sy) = (self.world_state.screen.0 as usize,
+                        self.world_state.screen.1 as usize);
+        ((cmp::min(x1, x2) + sx, cmp::min(y1, y2) + sy),
+         (cmp::max(x1, x2) + sx, cmp::max(y1, y2) + sy))
+    }
+
     fn move_delta(&mut self, xdelta: i32, ydelta: i32) {
-        self.world_state.screen =
-            (clamp(self.world_state.screen.0 + xdelta,
-                   self.constants.max_screen_move.0,
-                   0),
-             clamp(self.world_state.screen.1 + ydelta,
-                   self.constants.max_screen_move.1,
-                   0))
+        let screen = (clamp(self.world_state.screen.0 + xdelta,
+                            self.constants.max_screen_move.0,
+                            0),
+                      clamp(self.world_state.screen.1 + ydelta,
+                            self.constants.max_screen_move.1,
+                            0));
+        self.input(Input::Screen(screen));
     }
     fn handle_mouse(&mut self,
                     root: &mut RootConsole,
@@ -356,23 +519,31 @@ impl Game {
                     } else if p2_unset {
                         self.selection = (point1, mouse_pos);
                     } else if !p2_unset && !p1_unset {
-                        use life::Order::*;
-                        if let Some(order) =
+                        use master_of_destiny::life::Order::*;
+                        let area = self.selected_area();
+                        let cancel = matches!(self.partial_command,
+                                              PartialCommand::Cancel);
+                        if cancel {
+                            self.input(Input::Cancel(area));
+                        } else if let Some(order) =
                             self.world_state.commands.pop()
                         {
                             let new = match order {
-                                Mine(_) => Mine(self.selection),
+                                Mine(_) => Mine(area),
                                 BuildWall(_) => {
-                                    BuildWall(self.selection)
+                                    BuildWall(area)
                                 }
                                 BuildFence(_) => {
-                                    BuildFence(self.selection)
+                                    BuildFence(area)
                                 }
                                 BuildRamp(..) => {
-                                    BuildRamp(self.selection.0)
+                                    BuildRamp(area.0)
+                                }
+                                BuildStairs(..) => {
+                                    BuildStairs(area.0)
                                 }
                                 Go(_) => {
-                                    let (x, y) = self.selection.0;
+                                    let (x, y) = area.0;
                                     if let Some(ref ws) =
                                         self.world_state.map
                                     {
@@ -383,19 +554,21 @@ impl Game {
                                         Go((x, y, 100))
                                     }
                                 }
-                                CartGoods(_) => {
-                                    CartGoods(self.selection)
+                                CartGoods(_, filter) => {
+                                    CartGoods(area, filter)
                                 }
                                 GatherPlants(_) => {
-                                    GatherPlants(self.selection)
+                                    GatherPlants(area)
                                 }
                                 FellTrees(_) => {
-                                    FellTrees(self.selection)
+                                    FellTrees(area)
                                 }
+                                Destroy(_) => Destroy(area),
                             };
                             println!("{:?}", new);
-                            self.world_state.commands.push(new);
+                            self.input(Input::Order(new));
                         }
+                        self.selection = ((0, 0), (0, 0));
                         self.screen = GameScreen::Game;
                     }
                 } else if mouse.rbutton_pressed {
@@ -411,20 +584,31 @@ impl Game {
                         self.world_state.commands.push(
                             life::Order::BuildWall(((0,0), (0,0)))
                         );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
                     }
                     "fence" => {
                         self.world_state.commands.push(
                             life::Order::BuildFence(((0,0), (0,0)))
                         );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
                     }
                     "ramp" => {
                         self.world_state.commands.push(
                             life::Order::BuildRamp((0,0))
                         );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
+                    }
+                    "stairs" => {
+                        self.world_state.commands.push(
+                            life::Order::BuildStairs((0,0))
+                        );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
                     }
                 }
-                self.selection = ((0, 0), (0, 0));
-                self.screen = GameScreen::SelectArea;
             }
             GameScreen::SelectOtherCommand => {
                 menu_event!{
@@ -433,27 +617,72 @@ impl Game {
                         self.world_state.commands.push(
                             life::Order::GatherPlants(((0,0), (0,0)))
                         );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
                     }
                     "fell_trees" => {
                         self.world_state.commands.push(
                             life::Order::FellTrees(((0,0), (0,0)))
                         );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
+                    }
+                }
+            }
+            GameScreen::SelectMoveCommand => {
+                menu_event!{
+                    (mouse, self.move_commands)
+                    "go_to" => {
+                        self.world_state.commands.push(
+                            life::Order::Go((0, 0, 0))
+                        );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
+                    }
+                    "cart_goods" => {
+                        self.world_state.commands.push(
+                            life::Order::CartGoods(((0,0), (0,0)), None)
+                        );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
+                    }
+                    "cart_food" => {
+                        self.world_state.commands.push(
+                            life::Order::CartGoods(((0,0), (0,0)),
+                                                   Some(ItemCategory::Food))
+                        );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
+                    }
+                    "cart_materials" => {
+                        self.world_state.commands.push(
+                            life::Order::CartGoods(((0,0), (0,0)),
+                                                   Some(ItemCategory::Material))
+                        );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
+                    }
+                    "cart_tools" => {
+                        self.world_state.commands.push(
+                            life::Order::CartGoods(((0,0), (0,0)),
+                                                   Some(ItemCategory::Tool))
+                        );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
                     }
                 }
-                self.selection = ((0, 0), (0, 0));
-                self.screen = GameScreen::SelectArea;
             }
             GameScreen::SelectDiggable => {
                 menu_event!{
-                    (mouse, self.other_commands)
+                    (mouse, self.dig_commands)
                     "mine" => {
                         self.world_state.commands.push(
                             life::Order::Mine(((0,0), (0,0)))
                         );
+                        self.selection = ((0, 0), (0, 0));
+                        self.screen = GameScreen::SelectArea;
                     }
                 }
-                self.selection = ((0, 0), (0, 0));
-                self.screen = GameScreen::SelectArea;
             }
 
             GameScreen::Menu => {
@@ -463,7 +692,9 @@ impl Game {
                     "use_seed" => {
                         self.screen = GameScreen::GetSeed;
                     }
+                    "load_game" => { self.load_game() }
                     "resume" => {
+                        self.input(Input::Resume);
                         self.screen = GameScreen::Game
                     }
                     "exit" => { std::process::exit(0) }
@@ -482,6 +713,9 @@ impl Game {
                     "destroy" => {
                         self.partial_command =
                             PartialCommand::Destroy;
+                        self.world_state.commands.push(
+                            life::Order::Destroy(((0, 0), (0, 0)))
+                        );
                         self.screen =
                             GameScreen::SelectArea;
                     }
@@ -495,7 +729,7 @@ impl Game {
                         self.partial_command =
                             PartialCommand::Move;
                         self.screen =
-                            GameScreen::SelectArea;
+                            GameScreen::SelectMoveCommand;
                     }
                     "other" => {
                         self.partial_command =
@@ -529,20 +763,30 @@ impl Game {
                             self.show_hud = true;
                         }
                     }
+                    if self.speed_button
+                           .bbox_colliding(self.world_state.cursor)
+                           .is_some()
+                    {
+                        let next = self.speed.next();
+                        self.set_speed(next);
+                    }
                 }
             }
             GameScreen::Paused => {
                 menu_event!{
-                    (mouse, self.menu)
+                    (mouse, self.pause_menu)
                     "main_menu" => {
                         self.screen = GameScreen::Menu
                     }
+                    "save_game" => { self.save_game() }
+                    "load_game" => { self.load_game() }
                     "fullscreen" => {
                         let fullscreen =
                             root.is_fullscreen();
                         root.set_fullscreen(!fullscreen);
                     }
                     "back" => {
+                        self.input(Input::Resume);
                         self.screen = GameScreen::Game;
                     }
                     "exit" => {
@@ -557,6 +801,7 @@ impl Game {
     fn handle_key(&mut self, key: &input::Key) {
         match self.screen {
             GameScreen::SelectOtherCommand => {}
+            GameScreen::SelectMoveCommand => {}
             GameScreen::SelectCommand => {}
             GameScreen::SelectArea => {}
             GameScreen::SelectDiggable => {}
@@ -576,6 +821,7 @@ impl Game {
             GameScreen::Paused => {
                 match key.code {
                     KeyCode::Spacebar => {
+                        self.input(Input::Resume);
                         self.screen = GameScreen::Game
                     }
                     KeyCode::Escape => self.screen = GameScreen::Menu,
@@ -585,6 +831,7 @@ impl Game {
             GameScreen::Game => {
                 match key.code {
                     KeyCode::Spacebar => {
+                        self.input(Input::Pause);
                         self.screen = GameScreen::Paused
                     }
                     KeyCode::Tab => {
@@ -604,23 +851,42 @@ impl Game {
                                 self.screen =
                                     GameScreen::SelectCommand;
                             }
+                            'p' => {
+                                if self.speed == Speed::Paused {
+                                    self.set_speed(Speed::Times(1));
+                                } else {
+                                    self.set_speed(Speed::Paused);
+                                }
+                            }
+                            'f' => self.set_speed(Speed::UntilEvent),
                             '<' => {
-                                self.world_state.level =
+                                let level =
                                     clamp(self.world_state.level - 1,
                                           self.constants
                                               .highest_world,
                                           0);
+                                self.input(Input::Level(level));
                             }
                             '>' => {
-                                self.world_state.level =
+                                let level =
                                     clamp(self.world_state.level + 1,
                                           self.constants
                                               .highest_world,
                                           0);
+                                self.input(Input::Level(level));
                             }
                             _ => {}
                         };
                     }
+                    KeyCode::Number1 => {
+                        self.set_speed(Speed::Times(1))
+                    }
+                    KeyCode::Number2 => {
+                        self.set_speed(Speed::Times(2))
+                    }
+                    KeyCode::Number5 => {
+                        self.set_speed(Speed::Times(5))
+                    }
                     KeyCode::Up => self.move_delta(0, -MOVE_DIST),
                     KeyCode::Down => self.move_delta(0, MOVE_DIST),
                     KeyCode::Left => self.move_delta(-MOVE_DIST, 0),
@@ -665,11 +931,4 @@ fn main() {
         game.draw(&mut root);
         root.flush();
     }
-
-    unsafe {
-        match game.world_state.map {
-            Some(x) => x.delete_heightmap(),
-            None => {}
-        }
-    }
 }
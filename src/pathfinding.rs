@@ -0,0 +1,434 @@
+use std::cmp;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use physics::PhysicsActor;
+use utils::{Point2D, Point3D, strict_adjacent};
+use worldgen::World;
+use worldgen::terrain::{State, Structure, Tile};
+
+/// The most points a search looks at before giving up on finding a
+/// path, so that asking for somewhere out of reach does not search
+/// the whole map.
+const MAX_NODES: usize = 8192;
+/// What it costs to walk from one tile to the next on flat ground.
+const STEP_COST: usize = 10;
+/// What it costs on top of the step to scramble up or down a level
+/// without a ramp, or to climb the walls of a shaft.
+const CLIMB_COST: usize = 20;
+/// What it costs on top of the step to wade or swim through water.
+const WADE_COST: usize = 20;
+/// What it costs on top of the step to push through a plant, for
+/// each level of its height.
+const PLANT_COST: usize = 2;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct Node {
+    /// The cost of the path so far, plus the least the rest of it can
+    /// cost.
+    estimate: usize,
+    pos: Point3D,
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Node) -> Ordering {
+        other.estimate.cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A* search for the cheapest path between two points, in full 3D.
+/// Creatures stand in open tiles with something under their feet, and
+/// swim in water. They walk to the tile beside them on the same
+/// level, or scramble a level up or down onto it, which ramps make as
+/// easy as walking. Walls and fences can not be climbed. They only go
+/// straight up or down on stairs, or by climbing the walls of a dug
+/// shaft. Deep water, tall plants and climbing all slow them down.
+///
+/// Points that are not somewhere a creature can stand are moved to
+/// the nearest place in their column that is. Returns the path from
+/// start to goal, both included, or None if the goal can not be
+/// reached, or not without searching more than `MAX_NODES` points.
+pub fn find_path<'a>(map: &World,
+                     start: Point3D,
+                     goal: Point3D,
+                     can_move: Box<Fn(Point3D) -> bool + 'a>)
+    -> Option<Vec<Point3D>> {
+    let start = get!(stand_near(map, start));
+    let goal = get!(stand_near(map, goal));
+    let mut frontier = BinaryHeap::new();
+    frontier.push(Node {
+                      estimate: estimate(start, goal),
+                      pos: start,
+                  });
+    let mut came_from = HashMap::new();
+    let mut cost_so_far = HashMap::new();
+    cost_so_far.insert(start, 0);
+    let mut searched = 0;
+    while let Some(Node { estimate: est, pos }) = frontier.pop() {
+        if pos == goal {
+            return Some(rebuild(&came_from, start, goal));
+        }
+        let so_far = cost_so_far[&pos];
+        // A cheaper way here was found after this one was queued.
+        if est > so_far + estimate(pos, goal) {
+            continue;
+        }
+        searched += 1;
+        if searched > MAX_NODES {
+            return None;
+        }
+        for (next, cost) in moves(map, pos) {
+            if !can_move(next) {
+                continue;
+            }
+            let total = so_far + cost;
+            if cost_so_far.get(&next).map_or(true, |&c| total < c) {
+                cost_so_far.insert(next, total);
+                came_from.insert(next, pos);
+                frontier.push(Node {
+                                  estimate: total +
+                                      estimate(next, goal),
+                                  pos: next,
+                              });
+            }
+        }
+    }
+    None
+}
+
+/// The least a path between two points can cost. Every move goes at
+/// most one tile across and one level up or down, and costs at least
+/// a step.
+fn estimate((x1, y1, z1): Point3D, (x2, y2, z2): Point3D) -> usize {
+    let across = diff(x1, x2) + diff(y1, y2);
+    STEP_COST * cmp::max(across, diff(z1, z2))
+}
+
+fn diff(a: usize, b: usize) -> usize {
+    cmp::max(a, b) - cmp::min(a, b)
+}
+
+fn rebuild(came_from: &HashMap<Point3D, Point3D>,
+           start: Point3D,
+           goal: Point3D)
+    -> Vec<Point3D> {
+    let mut path = vec![goal];
+    let mut current = goal;
+    while current != start {
+        current = came_from[&current];
+        path.push(current);
+    }
+    path.reverse();
+    path
+}
+
+/// Everywhere a creature standing at a point can get to in one move,
+/// and what it costs to go there.
+fn moves(map: &World, (x, y, z): Point3D) -> Vec<(Point3D, usize)> {
+    let mut moves = vec![];
+    let here = tile_at(map, (x, y, z)).unwrap_or(Tile::Empty);
+    let under = below(map, (x, y, z));
+    let headroom = tile_at(map, (x, y, z + 1)).map_or(false, open);
+    for (ax, ay) in sides((x, y)) {
+        let side = match tile_at(map, (ax, ay, z)) {
+            Some(side) => side,
+            None => continue,
+        };
+        if can_stand(map, (ax, ay, z)) {
+            moves.push(((ax, ay, z), STEP_COST + slowness(side)));
+        } else if side.solid() {
+            // Up onto the side, unless it is a wall.
+            let up = (ax, ay, z + 1);
+            if headroom && !is_wall(side) && can_stand(map, up) {
+                let ramp = is_ramp(side) || is_ramp(under);
+                moves.push((up, climb(ramp) + slowness_at(map, up)));
+            }
+        } else if z > 0 && can_stand(map, (ax, ay, z - 1)) {
+            // Down off the edge onto the side.
+            let down = (ax, ay, z - 1);
+            let ramp = is_ramp(under) || is_ramp(below(map, down));
+            moves.push((down, climb(ramp) + slowness_at(map, down)));
+        }
+    }
+    let mut levels = vec![z + 1];
+    if z > 0 {
+        levels.push(z - 1);
+    }
+    for to in levels {
+        let to = (x, y, to);
+        if !can_stand(map, to) {
+            continue;
+        }
+        let there = tile_at(map, to).unwrap_or(Tile::Empty);
+        if is_stairs(here) || is_stairs(there) {
+            moves.push((to, STEP_COST + slowness(there)));
+        } else if is_shaft(map, (x, y, z)) && is_shaft(map, to) {
+            moves.push((to, climb(false) + slowness(there)));
+        }
+    }
+    moves
+}
+
+fn climb(ramp: bool) -> usize {
+    if ramp {
+        STEP_COST
+    } else {
+        STEP_COST + CLIMB_COST
+    }
+}
+
+/// What it costs on top of the step to move into a tile.
+fn slowness(tile: Tile) -> usize {
+    match tile {
+        Tile::Water(_, State::Liquid, _) => WADE_COST,
+        Tile::Vegetation(_, height, _) => {
+            PLANT_COST * cmp::max(height, 0) as usize
+        }
+        _ => 0,
+    }
+}
+
+fn slowness_at(map: &World, pnt: Point3D) -> usize {
+    tile_at(map, pnt).map_or(0, slowness)
+}
+
+/// The nearest point in the same column as a point where a creature
+/// can stand, if there is one.
+fn stand_near(map: &World, (x, y, z): Point3D) -> Option<Point3D> {
+    let height = get!(map.unit_at((x, y))).tiles.borrow().len();
+    let top = cmp::max(z, height);
+    (0..top + 1)
+        .flat_map(|d| {
+                      let mut zs = vec![z + d];
+                      if d > 0 && d <= z {
+                          zs.push(z - d);
+                      }
+                      zs
+                  })
+        .map(|z| (x, y, z))
+        .find(|&pnt| can_stand(map, pnt))
+}
+
+/// The tile at a point, which is empty above the top of a column, or
+/// None off the edge of the map.
+fn tile_at(map: &World, (x, y, z): Point3D) -> Option<Tile> {
+    map.unit_at((x, y)).map(|unit| {
+        unit.tiles.borrow().get(z).cloned().unwrap_or(Tile::Empty)
+    })
+}
+
+fn below(map: &World, (x, y, z): Point3D) -> Tile {
+    if z == 0 {
+        return Tile::Empty;
+    }
+    tile_at(map, (x, y, z - 1)).unwrap_or(Tile::Empty)
+}
+
+fn open(tile: Tile) -> bool { !tile.solid() }
+
+/// Tests if a creature can be at a point: in open space, with solid
+/// ground, ice, loose rubble or stairs under it, or in water, on
+/// stairs, or holding on to the walls of a shaft.
+fn can_stand(map: &World, (x, y, z): Point3D) -> bool {
+    let here = match tile_at(map, (x, y, z)) {
+        Some(here) => here,
+        None => return false,
+    };
+    if !open(here) {
+        return false;
+    }
+    if z == 0 || is_stairs(here) ||
+        matches!(here, Tile::Water(_, State::Liquid, _))
+    {
+        return true;
+    }
+    match below(map, (x, y, z)) {
+        Tile::Moveable(..) |
+        Tile::Water(_, State::Solid, _) => true,
+        under if under.solid() || is_stairs(under) => true,
+        _ => is_shaft(map, (x, y, z)),
+    }
+}
+
+/// Tests if a point is in a shaft: open, with solid walls on every
+/// side to climb.
+fn is_shaft(map: &World, (x, y, z): Point3D) -> bool {
+    tile_at(map, (x, y, z)).map_or(false, open) &&
+        sides((x, y)).into_iter().all(|(ax, ay)| {
+            tile_at(map, (ax, ay, z)).map_or(true, |t| t.solid())
+        })
+}
+
+/// The columns beside a point.
+fn sides((x, y): Point2D) -> Vec<Point2D> {
+    strict_adjacent((x, y))
+        .into_iter()
+        .filter(|&pos| pos != (x, y))
+        .collect()
+}
+
+fn is_ramp(tile: Tile) -> bool {
+    match tile {
+        Tile::Ramp(..) |
+        Tile::Construction(_, Structure::Ramp) => true,
+        _ => false,
+    }
+}
+
+fn is_stairs(tile: Tile) -> bool {
+    matches!(tile, Tile::Construction(_, Structure::Stairs))
+}
+
+fn is_wall(tile: Tile) -> bool {
+    match tile {
+        Tile::Construction(_, Structure::Wall) |
+        Tile::Construction(_, Structure::Fence) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use worldgen::terrain::{IgneousRocks, Material, RestrictedTile,
+                            StoneTypes};
+
+    const BASALT: StoneTypes =
+        StoneTypes::Igneous(IgneousRocks::Basalt);
+
+    fn rock(height: usize) -> Vec<Tile> {
+        vec![Tile::Stone(BASALT, State::Solid); height]
+    }
+
+    fn with_top(height: usize, top: Tile) -> Vec<Tile> {
+        let mut column = rock(height);
+        column.push(top);
+        column
+    }
+
+    fn path(map: &World, start: Point3D, goal: Point3D)
+        -> Option<Vec<Point3D>> {
+        find_path(map, start, goal, Box::new(|_| true))
+    }
+
+    /// Rows of open floor, with walls between them that each have a
+    /// gap at alternate ends, so there is only one long way through.
+    fn maze(rows: usize, width: usize) -> World {
+        let wall =
+            with_top(1,
+                     Tile::Construction(Material::Stone(BASALT),
+                                        Structure::Wall));
+        let columns = (0..rows * 2 - 1)
+            .map(|y| {
+                let gap = if y % 4 == 1 { width - 1 } else { 0 };
+                (0..width)
+                    .map(|x| {
+                        if y % 2 == 0 || x == gap {
+                            rock(1)
+                        } else {
+                            wall.clone()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        World::from_columns(columns)
+    }
+
+    #[test]
+    fn ramps_lead_up_and_down() {
+        let ramp = Tile::Ramp(RestrictedTile::Stone(BASALT,
+                                                    State::Solid));
+        let map = World::from_columns(vec![vec![rock(1),
+                                                with_top(1, ramp),
+                                                rock(2)]]);
+        assert_eq!(path(&map, (0, 0, 1), (2, 0, 2)),
+                   Some(vec![(0, 0, 1), (1, 0, 2), (2, 0, 2)]));
+        assert_eq!(path(&map, (2, 0, 2), (0, 0, 1)),
+                   Some(vec![(2, 0, 2), (1, 0, 2), (0, 0, 1)]));
+    }
+
+    #[test]
+    fn walls_can_not_be_climbed() {
+        let wall = Tile::Construction(Material::Stone(BASALT),
+                                      Structure::Wall);
+        let map = World::from_columns(vec![vec![rock(1),
+                                                with_top(1, wall),
+                                                rock(2)]]);
+        assert_eq!(path(&map, (0, 0, 1), (2, 0, 2)), None);
+    }
+
+    #[test]
+    fn stairs_go_straight_up() {
+        let stairs = Tile::Construction(Material::Stone(BASALT),
+                                        Structure::Stairs);
+        let mut stairwell = rock(1);
+        stairwell.extend(vec![stairs; 3]);
+        let map = World::from_columns(vec![vec![rock(1),
+                                                stairwell,
+                                                rock(4)]]);
+        assert_eq!(path(&map, (1, 0, 1), (2, 0, 4)),
+                   Some(vec![(1, 0, 1),
+                             (1, 0, 2),
+                             (1, 0, 3),
+                             (1, 0, 4),
+                             (2, 0, 4)]));
+        assert_eq!(path(&map, (0, 0, 1), (2, 0, 4)).map(|p| p.len()),
+                   Some(6));
+    }
+
+    #[test]
+    fn shafts_can_be_climbed() {
+        let map = World::from_columns(vec![vec![rock(4),
+                                                rock(4),
+                                                rock(4)],
+                                           vec![rock(4),
+                                                rock(1),
+                                                rock(4)],
+                                           vec![rock(4),
+                                                rock(4),
+                                                rock(4)]]);
+        assert_eq!(path(&map, (1, 1, 1), (0, 1, 4)),
+                   Some(vec![(1, 1, 1),
+                             (1, 1, 2),
+                             (1, 1, 3),
+                             (0, 1, 4)]));
+        assert_eq!(path(&map, (0, 1, 4), (1, 1, 1)),
+                   Some(vec![(0, 1, 4),
+                             (1, 1, 3),
+                             (1, 1, 2),
+                             (1, 1, 1)]));
+    }
+
+    #[test]
+    fn out_of_reach_goals_have_no_path() {
+        let map = World::from_columns(vec![vec![rock(1),
+                                                rock(1),
+                                                rock(5)]]);
+        assert_eq!(path(&map, (0, 0, 1), (2, 0, 5)), None);
+    }
+
+    #[test]
+    fn long_searches_give_up() {
+        // The way out of a maze is at the far end of its last row.
+        let exit = |rows: usize, width: usize| {
+            let x = if rows % 2 == 1 { width - 1 } else { 0 };
+            (x, rows * 2 - 2, 1)
+        };
+        let small = maze(5, 10);
+        assert_eq!(path(&small, (0, 0, 1), exit(5, 10))
+                       .map(|p| p.len()),
+                   Some(5 * 10 + 4));
+        // Every tile of this one has to be searched to get out, and
+        // there are more of them than a search looks at.
+        let rows = MAX_NODES / 100 + 10;
+        let large = maze(rows, 100);
+        assert_eq!(path(&large, (0, 0, 1), exit(rows, 100)), None);
+    }
+}
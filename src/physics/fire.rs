@@ -0,0 +1,251 @@
+extern crate rand;
+
+use std;
+use std::collections::BTreeMap;
+
+use self::rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use life::Mission;
+use life::animal::Species;
+use time::{Season, Weather};
+use utils::{Point3D, distance, strict_adjacent};
+use weather::Front;
+use worldgen::{World, WorldState};
+use worldgen::terrain::*;
+
+/// The chance each tick that fire spreads to something that burns
+/// next to it, on the driest of days.
+const SPREAD_CHANCE: f32 = 0.05;
+/// How many times likelier fire is to spread downwind than across
+/// the wind, and how many times less likely upwind.
+const WIND_FACTOR: f32 = 3.0;
+/// The chance each tick that a fire burns itself out. Trees burn four
+/// times as long.
+const BURN_OUT_CHANCE: f32 = 0.02;
+/// The chance each tick that rain or snow puts out a fire, under the
+/// heaviest of fronts.
+const DOUSE_CHANCE: f32 = 0.1;
+/// How badly animals want to get away from a fire they can see.
+const FLEE_PRIORITY: usize = 900;
+
+/// The fires burning on the map. Only these are looked at each tick.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Fires {
+    scanned: bool,
+    /// What was burning at each point before it caught fire. Saved as
+    /// a list of pairs, since JSON keys can only be strings.
+    #[serde(serialize_with = "save_burning",
+            deserialize_with = "load_burning")]
+    burning: BTreeMap<Point3D, Tile>,
+    /// Points set alight from elsewhere since the last tick.
+    sparks: Vec<Point3D>,
+}
+
+impl Fires {
+    /// Sets whatever is at a point alight next tick, if it burns.
+    pub fn light(&mut self, pnt: Point3D) { self.sparks.push(pnt); }
+
+    fn scan(&mut self, world: &World) {
+        for (y, row) in world.map.iter().enumerate() {
+            for (x, unit) in row.iter().enumerate() {
+                let tiles = unit.tiles.borrow();
+                for (z, tile) in tiles.iter().enumerate() {
+                    if *tile == Tile::Fire {
+                        self.burning.insert((x, y, z), Tile::Fire);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn save_burning<S>(burning: &BTreeMap<Point3D, Tile>,
+                   serializer: S)
+    -> Result<S::Ok, S::Error>
+    where S: Serializer
+{
+    burning.iter().collect::<Vec<_>>().serialize(serializer)
+}
+
+fn load_burning<'de, D>(deserializer: D)
+    -> Result<BTreeMap<Point3D, Tile>, D::Error>
+    where D: Deserializer<'de>
+{
+    let pairs = Vec::<(Point3D, Tile)>::deserialize(deserializer)?;
+    Ok(pairs.into_iter().collect())
+}
+
+/// Moves the fires on the map on by a tick. Fire spreads to plants
+/// and wooden constructions around it, more readily the drier the
+/// weather and season and with the wind behind it. It burns out in a
+/// while, or sooner in rain, snow or next to water, leaving the soil
+/// it burned on covered in ash. Plants grow back in time. Animals
+/// that can see a fire run from it.
+pub fn run(ws: &mut WorldState) {
+    let fires = &mut ws.fires;
+    let flow = &mut ws.flow;
+    let settling = &mut ws.settling;
+    let rng = &mut ws.rng.physics;
+    let front = ws.weather;
+    let dry = dryness(&front, ws.time.calendar.season);
+    let world = match ws.map {
+        Some(ref mut world) => world,
+        None => return,
+    };
+    if !fires.scanned {
+        fires.scan(world);
+        fires.scanned = true;
+    }
+    let falling = front.weather == Weather::Raining ||
+        front.weather == Weather::Snowing;
+    let mut sparks = std::mem::replace(&mut fires.sparks, vec![]);
+    let burning = fires.burning
+                       .iter()
+                       .map(|(&pnt, &fuel)| (pnt, fuel))
+                       .collect::<Vec<_>>();
+    for (pnt, fuel) in burning {
+        let near = near(pnt);
+        let wet = near.iter().any(|&p| match world.tile_at(p) {
+                                      Some(Tile::Water(_, s, _)) => {
+                                          s == State::Liquid
+                                      }
+                                      _ => false,
+                                  });
+        let doused = wet ||
+            (falling &&
+                 rng.gen::<f32>() < DOUSE_CHANCE * front.intensity);
+        if doused || rng.gen::<f32>() < burn_out_chance(fuel) {
+            fires.burning.remove(&pnt);
+            burn_out(world, pnt);
+            flow.wake(pnt);
+            settling.disturb(pnt);
+            continue;
+        }
+        for next in near {
+            let chance = SPREAD_CHANCE * dry *
+                wind_factor(front.wind, pnt, next);
+            if world.tile_at(next).map_or(false, burns) &&
+               rng.gen::<f32>() < chance
+            {
+                sparks.push(next);
+            }
+        }
+    }
+    for pnt in sparks {
+        if let Some(tile) = world.tile_at(pnt) {
+            if burns(tile) {
+                world.replace(pnt, Tile::Fire);
+                world.take_stock(pnt);
+                fires.burning.insert(pnt, tile);
+            }
+        }
+    }
+    if fires.burning.is_empty() {
+        return;
+    }
+    for actor in &world.life {
+        let mut actor = actor.borrow_mut();
+        let fleeing = matches!(actor.current_goal(),
+                               Some(Mission::Flee(..)));
+        if fleeing || actor.species().species == Species::Dwarf {
+            continue;
+        }
+        let (x, y, _) = actor.current_pos();
+        let sight = actor.species().sight as f32;
+        let fire = fires.burning.keys().find(|&&(fx, fy, _)| {
+            distance((x, y), (fx, fy)) <= sight
+        });
+        if let Some(&(fx, fy, _)) = fire {
+            actor.add_goal(Mission::Flee((fx, fy), FLEE_PRIORITY));
+        }
+    }
+}
+
+/// Whether a tile catches fire: plants, and anything made of wood,
+/// be it built or lying about as logs.
+fn burns(tile: Tile) -> bool {
+    match tile {
+        Tile::Vegetation(..) |
+        Tile::Construction(Material::Wood(_), _) |
+        Tile::Item(Item::Material(Material::Wood(_))) => true,
+        _ => false,
+    }
+}
+
+fn burn_out_chance(fuel: Tile) -> f32 {
+    match fuel {
+        Tile::Vegetation(vt, ..) if vt.is_tree() => {
+            BURN_OUT_CHANCE / 4.0
+        }
+        _ => BURN_OUT_CHANCE,
+    }
+}
+
+/// Puts out the fire at a point, turning the soil under it to ash.
+/// Nothing is left of what burnt, be it a tree, a wall or a log.
+fn burn_out(world: &mut World, (x, y, z): Point3D) {
+    if world.tile_at((x, y, z)) != Some(Tile::Fire) {
+        return;
+    }
+    world.replace((x, y, z), Tile::Empty);
+    if z == 0 {
+        return;
+    }
+    if let Some(Tile::Stone(StoneTypes::Soil(_), State::Solid)) =
+        world.tile_at((x, y, z - 1))
+    {
+        let ash = StoneTypes::Soil(SoilTypes::Ash);
+        world.place((x, y, z - 1), Tile::Stone(ash, State::Solid));
+    }
+}
+
+/// How readily things burn in the weather and season, from 0 to 1.
+fn dryness(front: &Front, season: Season) -> f32 {
+    let weather = match front.weather {
+        Weather::Sunny => 1.0,
+        Weather::Overcast => 0.7,
+        Weather::Raining | Weather::Snowing => 0.2,
+    };
+    let season = match season {
+        Season::Summer => 1.0,
+        Season::Autumn => 0.8,
+        Season::Spring => 0.5,
+        Season::Winter => 0.3,
+    };
+    weather * season
+}
+
+/// How much likelier the wind makes fire to spread from one point to
+/// another.
+fn wind_factor(wind: Compass,
+               (x, y, _): Point3D,
+               (nx, ny, _): Point3D)
+    -> f32 {
+    if wind.opposite().step((x, y)) == Some((nx, ny)) {
+        WIND_FACTOR
+    } else if wind.step((x, y)) == Some((nx, ny)) {
+        1.0 / WIND_FACTOR
+    } else {
+        1.0
+    }
+}
+
+/// The points fire can reach from a point: above and below it, and
+/// beside it on its own level and a level up or down, where the
+/// ground slopes.
+fn near((x, y, z): Point3D) -> Vec<Point3D> {
+    let mut points = vec![(x, y, z + 1)];
+    if z > 0 {
+        points.push((x, y, z - 1));
+    }
+    for (ax, ay) in strict_adjacent((x, y)) {
+        if (ax, ay) == (x, y) {
+            continue;
+        }
+        for az in z.saturating_sub(1)..z + 2 {
+            points.push((ax, ay, az));
+        }
+    }
+    points
+}
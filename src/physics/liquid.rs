@@ -1,85 +1,669 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-use worldgen::Unit;
+extern crate rand;
+
+use std::cmp;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+use self::rand::Rng;
+
+use life::{Drinkable, Mission};
+use physics::PhysicsActor;
+use physics::fire::Fires;
+use utils::{Point3D, strict_adjacent};
+use worldgen::{World, WorldState};
 use worldgen::terrain::*;
 
+/// The most tiles of liquid searched for a way out when the liquid
+/// above them presses down.
+const PRESSURE_REACH: usize = 64;
+/// The most such searches made in a tick. The rest wait their turn
+/// in later ticks, so that a whole sea settling costs no more than a
+/// few searches each tick.
+const PRESSURE_SEARCHES: usize = 16;
+/// The chance that molten stone which can move does so in a tick. It
+/// is thick, and creeps along far slower than water runs.
+const LAVA_FLUIDITY: f32 = 0.1;
+/// How much thirst each level of water drunk quenches.
+const SATISFACTION: i32 = 1500;
+
+/// What became of a liquid tile in a tick.
+enum Step {
+    /// It flowed, or some of it did, to the given point.
+    Flowed(Point3D),
+    /// It has nowhere to go.
+    Settled,
+    /// It may be pushed somewhere, once there is time to look.
+    Pressing,
+    /// It can move, but has not yet.
+    Waiting,
+}
+
+/// Something that holds liquid, such as a bowl or a bucket. Whatever
+/// is poured into it mixes with what is already there.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Container<L: Liquid<L>> {
     size: i32,
-    name: &'static str,
-    liquids: Vec<L>,
+    liquid: Option<L>,
 }
 
 impl<L> Container<L>
     where
-    L: Liquid<L>,
+    L: Liquid<L> + Copy,
 {
-    fn contents(&self) -> i32 {
-        self.liquids
-            .iter()
-            .fold(0, |sum, x| sum + x.quantity())
+    /// An empty container that holds up to `size` of a liquid.
+    pub fn new(size: i32) -> Container<L> {
+        Container {
+            size: size,
+            liquid: None,
+        }
     }
-    fn is_full(&self) -> bool { self.contents() < self.size }
 
-    fn can_fit(&self, liq: &L) -> bool {
-        self.contents() + liq.quantity() < self.size
+    /// How much liquid is in it.
+    pub fn contents(&self) -> i32 {
+        self.liquid.map_or(0, |l| l.quantity())
     }
 
-    fn is_empty(&self) -> bool { self.liquids.len() == 0 }
+    /// What is in it, if anything.
+    pub fn liquid(&self) -> Option<L> { self.liquid }
+
+    pub fn is_full(&self) -> bool { self.contents() >= self.size }
+
+    /// How much more liquid it has room for.
+    pub fn room(&self) -> i32 { self.size - self.contents() }
 
-    fn fill(&mut self, liq: L) -> bool {
-        if self.can_fit(&liq) {
-            self.liquids.push(liq);
-            true
-        } else if self.is_full() {
-            false
+    pub fn can_fit(&self, liq: &L) -> bool {
+        self.contents() + liq.quantity() <= self.size
+    }
+
+    pub fn is_empty(&self) -> bool { self.contents() == 0 }
+
+    /// Pours as much of a liquid in as will fit. Returns false if
+    /// none of it did.
+    pub fn fill(&mut self, liq: L) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        let liq = if self.can_fit(&liq) {
+            liq
         } else {
-            let nliq = liq.new(self.size - self.contents());
-            self.fill(nliq)
+            liq.with_quantity(self.room())
+        };
+        self.liquid = Some(match self.liquid {
+                               Some(held) => held.mix(&liq),
+                               None => liq,
+                           });
+        true
+    }
+
+    /// Pours up to `quantity` of what is in it out, if it holds
+    /// anything.
+    pub fn pour(&mut self, quantity: i32) -> Option<L> {
+        let held = match self.liquid {
+            Some(held) => held,
+            None => return None,
+        };
+        let left = held.quantity() - quantity;
+        if left > 0 {
+            self.liquid = Some(held.with_quantity(left));
+            Some(held.with_quantity(quantity))
+        } else {
+            self.liquid = None;
+            Some(held)
         }
     }
 }
 
 pub trait Liquid<L: Liquid<L>> {
     fn quantity(&self) -> i32;
-    fn amount(&self) -> i32;
-    fn new(&self, quantity: i32) -> L;
+    /// The same liquid, in a different quantity.
+    fn with_quantity(&self, quantity: i32) -> L;
+    /// What the liquid becomes with another poured into it.
+    fn mix(&self, other: &L) -> L;
 }
 
-pub fn solid_physics(_pnt: (usize, usize),
-                     _aj: Vec<Rc<Unit>>)
-                     -> Option<Vec<Unit>> {
-    None
+/// Water taken out of the map to carry about: as much as a
+/// `Tile::Water` of the same purity and depth holds.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Draught {
+    pub purity: LiquidPurity,
+    pub depth: Depth,
 }
-pub fn liquid_physics(_pnt: (usize, usize),
-                      aj: Vec<Rc<Unit>>)
-                      -> Option<Vec<Unit>> {
-    Some(
-        aj.iter()
-            .map(|u| {
-                let ut = u.tiles.borrow_mut();
-                let len = ut.len();
-                let new_height = len % 23;
-                if new_height <= len {
-                    Unit {
-                        biome: u.biome.clone(),
-                        tiles: RefCell::new(ut.iter()
-                                            .take(new_height)
-                                            .map(|x| *x)
-                                            .collect()),
-                    }
-                } else {
-                    let mut tiles = ut;
-                    for x in 0..(new_height - len) {
-                        tiles.push(Tile::Water(LiquidPurity::Clean,
-                                               State::Liquid,
-                                               (len + x) as i32));
-                    }
-                    Unit {
-                        biome: u.biome.clone(),
-                        tiles: RefCell::new(tiles.to_vec()),
+
+impl Draught {
+    /// What can be drawn from a tile, if it is liquid water.
+    pub fn from_tile(tile: Tile) -> Option<Draught> {
+        match tile {
+            Tile::Water(purity, State::Liquid, depth) => {
+                Some(Draught {
+                         purity: purity,
+                         depth: depth,
+                     })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Liquid<Draught> for Draught {
+    fn quantity(&self) -> i32 { self.depth }
+
+    fn with_quantity(&self, quantity: i32) -> Draught {
+        Draught {
+            purity: self.purity,
+            depth: quantity,
+        }
+    }
+
+    /// Water is as dirty as the dirtiest of what was mixed.
+    fn mix(&self, other: &Draught) -> Draught {
+        Draught {
+            purity: cmp::max(self.purity, other.purity),
+            depth: self.depth + other.depth,
+        }
+    }
+}
+
+impl Drinkable for Draught {
+    /// Straining takes out the sand and mud.
+    fn filter(self) -> Draught {
+        let purity = match self.purity {
+            LiquidPurity::Sandy | LiquidPurity::Muddy => {
+                LiquidPurity::Clean
+            }
+            purity => purity,
+        };
+        Draught { purity: purity, ..self }
+    }
+
+    /// Boiling kills whatever lives in it, but does nothing for
+    /// poison.
+    fn boild(self) -> Draught {
+        let purity = match self.purity {
+            LiquidPurity::Dirty | LiquidPurity::Murky => {
+                LiquidPurity::Clean
+            }
+            purity => purity,
+        };
+        Draught { purity: purity, ..self }
+    }
+
+    fn satisfaction(&self) -> i32 { self.depth * SATISFACTION }
+}
+
+/// The liquid tiles that may still move. Only these are looked at
+/// each tick, so a sea at rest costs nothing until something next to
+/// it changes. Anything that changes the map outside of the physics
+/// has to wake the liquid around the change.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Flow {
+    /// Whether the whole map has been looked over for liquid that can
+    /// move, which is done once for a new or loaded map.
+    scanned: bool,
+    awake: BTreeSet<Point3D>,
+    /// Liquid waiting for a search for where it is pushed out to, in
+    /// the order it started waiting.
+    pressing: VecDeque<Point3D>,
+    queued: BTreeSet<Point3D>,
+}
+
+impl Flow {
+    /// Wakes the liquid at a point and around it: the tile above,
+    /// which may now fall into it, the tile below, which may now be
+    /// the top of a body of liquid pressing down, and its neighbours
+    /// on the same level, which may now spread into it.
+    pub fn wake(&mut self, (x, y, z): Point3D) {
+        self.awake.insert((x, y, z));
+        self.awake.insert((x, y, z + 1));
+        if z > 0 {
+            self.awake.insert((x, y, z - 1));
+        }
+        for (ax, ay) in strict_adjacent((x, y)) {
+            self.awake.insert((ax, ay, z));
+        }
+    }
+
+    /// Wakes the top of the body of liquid that a point is part of,
+    /// or as much of it as is close by. Liquid has left the point, so
+    /// the weight of the body may now push more of it down and out.
+    fn wake_surface(&mut self, world: &World, start: Point3D) {
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(start);
+        queue.push_back(start);
+        while let Some((x, y, z)) = queue.pop_front() {
+            if seen.len() > PRESSURE_REACH {
+                return;
+            }
+            if space_at(world, (x, y, z + 1)) != Some(FULL_DEPTH) {
+                self.awake.insert((x, y, z + 1));
+                self.awake.insert((x, y, z));
+            }
+            for next in around((x, y, z)) {
+                if next.2 >= start.2 && !seen.contains(&next) &&
+                   space_at(world, next) == Some(FULL_DEPTH)
+                {
+                    seen.insert(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    fn press(&mut self, pnt: Point3D) {
+        if self.queued.insert(pnt) {
+            self.pressing.push_back(pnt);
+        }
+    }
+
+    /// Records what became of the liquid that was at a point.
+    fn moved(&mut self, world: &World, pnt: Point3D, step: Step) {
+        match step {
+            Step::Flowed(to) => {
+                self.wake(pnt);
+                self.wake(to);
+                self.wake_surface(world, pnt);
+            }
+            Step::Pressing => self.press(pnt),
+            Step::Waiting => {
+                self.awake.insert(pnt);
+            }
+            Step::Settled => settle_purity(world, pnt),
+        }
+    }
+
+    /// Wakes every liquid tile on the map that can flow somewhere,
+    /// and all the molten stone, which is rare enough to look over
+    /// once.
+    fn scan(&mut self, world: &World) {
+        for (y, row) in world.map.iter().enumerate() {
+            for (x, unit) in row.iter().enumerate() {
+                let len = unit.tiles.borrow().len();
+                for z in 0..len {
+                    let pnt = (x, y, z);
+                    if let Some((_, depth)) = liquid_at(world, pnt) {
+                        if !outlets(world, pnt, depth).is_empty() {
+                            self.awake.insert(pnt);
+                        }
+                    } else if is_lava(world.tile_at(pnt)) {
+                        self.awake.insert(pnt);
                     }
                 }
-            })
-            .collect(),
-    )
+            }
+        }
+    }
+}
+
+/// Moves the liquids on the map on by a tick. Water works like a
+/// cellular automaton over tiles holding from 1 to `FULL_DEPTH`
+/// levels of it: it falls into any room below, spreads a level at a
+/// time to neighbours at least two levels shallower, and, when a full
+/// column of it presses down on more full water, it is pushed out of
+/// the nearest opening lower than itself, which fills U-bends and
+/// channels dug off a lake up to just below the lake's surface.
+/// Molten stone flows too, after its own fashion, and burns to death
+/// anything alive that it touches.
+pub fn run(ws: &mut WorldState) {
+    let world = match ws.map {
+        Some(ref world) => world,
+        None => return,
+    };
+    let flow = &mut ws.flow;
+    let fires = &mut ws.fires;
+    let settling = &mut ws.settling;
+    let rng = &mut ws.rng.physics;
+    if !flow.scanned {
+        flow.scan(world);
+        flow.scanned = true;
+    }
+    let mut cells = flow.awake.iter().cloned().collect::<Vec<_>>();
+    flow.awake.clear();
+    // Take the tiles in a random order, so that water does not drift
+    // towards the corner of the map that is updated first.
+    rng.shuffle(&mut cells);
+    for pnt in cells {
+        let moved = step(world, pnt, fires, rng, false);
+        if let Step::Flowed(to) = moved {
+            if unsettles(world, pnt, to) {
+                settling.disturb(pnt);
+            }
+        }
+        flow.moved(world, pnt, moved);
+    }
+    for _ in 0..PRESSURE_SEARCHES {
+        let pnt = match flow.pressing.pop_front() {
+            Some(pnt) => pnt,
+            None => break,
+        };
+        flow.queued.remove(&pnt);
+        let moved = step(world, pnt, fires, rng, true);
+        if let Step::Flowed(to) = moved {
+            if unsettles(world, pnt, to) {
+                settling.disturb(pnt);
+            }
+        }
+        flow.moved(world, pnt, moved);
+    }
+    for actor in &world.life {
+        let mut actor = actor.borrow_mut();
+        let pos = actor.current_pos();
+        let burning = is_lava(world.tile_at(pos)) ||
+            around(pos)
+                .into_iter()
+                .any(|pnt| is_lava(world.tile_at(pnt)));
+        if burning {
+            actor.add_goal(Mission::Die);
+        }
+    }
+}
+
+/// Whether liquid that flowed from one point to another can have
+/// brought down something around it. Molten stone running off can
+/// leave the rock above it with nothing underneath, and water that
+/// drains away leaves whatever floated on it hanging in the air.
+fn unsettles(world: &World, (x, y, z): Point3D, to: Point3D) -> bool {
+    let above = world.tile_at((x, y, z + 1));
+    is_lava(world.tile_at(to)) ||
+        above.map_or(false, |t| t.falls_into(&Tile::Empty))
+}
+
+/// Moves whatever liquid is at a point, if it can go anywhere.
+fn step<R: Rng>(world: &World,
+                pnt: Point3D,
+                fires: &mut Fires,
+                rng: &mut R,
+                search: bool)
+    -> Step {
+    match world.tile_at(pnt) {
+        Some(Tile::Water(purity, State::Liquid, depth)) => {
+            flow_water(world, pnt, purity, depth, rng, search)
+        }
+        Some(Tile::Stone(kind, State::Liquid)) => {
+            flow_lava(world, pnt, kind, fires, rng)
+        }
+        _ => Step::Settled,
+    }
+}
+
+/// Moves water at a point. Looking for an opening for it to be
+/// pushed out of is only done if `search` is set.
+fn flow_water<R: Rng>(world: &World,
+                      pnt: Point3D,
+                      purity: LiquidPurity,
+                      depth: Depth,
+                      rng: &mut R,
+                      search: bool)
+    -> Step {
+    let to = match rng.choose(&outlets(world, pnt, depth)) {
+        Some(&to) => to,
+        None if !presses_down(world, pnt) => {
+            return Step::Settled
+        }
+        None if !search => return Step::Pressing,
+        None => {
+            match pressed(world, pnt) {
+                Some(to) => to,
+                None => return Step::Settled,
+            }
+        }
+    };
+    let room = match space_at(world, to) {
+        Some(d) => FULL_DEPTH - d,
+        None => return Step::Settled,
+    };
+    let amount = if to.2 == pnt.2 {
+        1
+    } else {
+        cmp::min(depth, room)
+    };
+    // Moving water carries its purity along with it, and whatever it
+    // flows into is as dirty as the dirtier of the two.
+    let into = liquid_at(world, to)
+        .map_or(purity, |(p, _)| cmp::max(p, purity));
+    let into = world.purity(to, into, true);
+    set_liquid(world, pnt, purity, depth - amount);
+    set_liquid(world, to, into, FULL_DEPTH - room + amount);
+    Step::Flowed(to)
+}
+
+/// Lets the water at a point take on the purity of what is around it,
+/// now that it has come to rest.
+fn settle_purity(world: &World, pnt: Point3D) {
+    if let Some((purity, depth)) = liquid_at(world, pnt) {
+        let purity = world.purity(pnt, purity, false);
+        set_liquid(world, pnt, purity, depth);
+    }
+}
+
+/// Moves molten stone at a point. It comes in whole tiles, and never
+/// spreads out to fill a hollow the way water does: it only falls
+/// into open air, or runs off a ledge, which lets a pile of it slump
+/// until it lies a tile deep. Where it touches water it cools into
+/// obsidian, and it sets the plants around it alight.
+fn flow_lava<R: Rng>(world: &World,
+                     pnt: Point3D,
+                     kind: StoneTypes,
+                     fires: &mut Fires,
+                     rng: &mut R)
+    -> Step {
+    let near = around(pnt);
+    let quenched = near.iter().any(|&p| {
+        matches!(world.tile_at(p), Some(Tile::Water(..)))
+    });
+    if quenched {
+        let obsidian = StoneTypes::Igneous(IgneousRocks::Obsidian);
+        set_tile(world, pnt, Tile::Stone(obsidian, State::Solid));
+        return Step::Settled;
+    }
+    for &p in &near {
+        if matches!(world.tile_at(p), Some(Tile::Vegetation(..))) {
+            fires.light(p);
+        }
+    }
+    let to = match rng.choose(&lava_outlets(world, pnt)) {
+        Some(&to) => to,
+        None => return Step::Settled,
+    };
+    if rng.gen::<f32>() >= LAVA_FLUIDITY {
+        return Step::Waiting;
+    }
+    set_tile(world, pnt, Tile::Empty);
+    set_tile(world, to, Tile::Stone(kind, State::Liquid));
+    Step::Flowed(to)
+}
+
+/// Where molten stone at a point can go: straight down into open air,
+/// or else sideways into open air with more open air below it.
+fn lava_outlets(world: &World, (x, y, z): Point3D) -> Vec<Point3D> {
+    if z == 0 {
+        return vec![];
+    }
+    let open = |pnt| space_at(world, pnt) == Some(0);
+    if open((x, y, z - 1)) {
+        return vec![(x, y, z - 1)];
+    }
+    strict_adjacent((x, y))
+        .into_iter()
+        .filter(|&pos| pos != (x, y))
+        .filter(|&(ax, ay)| {
+                    open((ax, ay, z)) && open((ax, ay, z - 1))
+                })
+        .map(|(ax, ay)| (ax, ay, z))
+        .collect()
+}
+
+/// Where liquid at a point can flow to by itself: straight down if
+/// there is room there, or else to whichever of its neighbours are
+/// shallowest, as long as they are at least two levels shallower.
+fn outlets(world: &World,
+           (x, y, z): Point3D,
+           depth: Depth)
+    -> Vec<Point3D> {
+    if z > 0 {
+        if let Some(below) = space_at(world, (x, y, z - 1)) {
+            if below < FULL_DEPTH {
+                return vec![(x, y, z - 1)];
+            }
+        }
+    }
+    let sides = strict_adjacent((x, y))
+        .into_iter()
+        .filter(|&pos| pos != (x, y))
+        .filter_map(|(ax, ay)| {
+                        space_at(world, (ax, ay, z))
+                            .map(|d| ((ax, ay, z), d))
+                    })
+        .filter(|&(_, d)| d < depth - 1)
+        .collect::<Vec<_>>();
+    let lowest = sides.iter().map(|&(_, d)| d).min();
+    sides.into_iter()
+         .filter(|&(_, d)| Some(d) == lowest)
+         .map(|(pnt, _)| pnt)
+         .collect()
+}
+
+/// Whether liquid at a point presses down on the liquid below it: it
+/// is the top tile of a body of liquid, resting on full liquid.
+fn presses_down(world: &World, (x, y, z): Point3D) -> bool {
+    z > 0 && liquid_at(world, (x, y, z + 1)).is_none() &&
+    space_at(world, (x, y, z - 1)) == Some(FULL_DEPTH)
+}
+
+/// Where liquid that presses down is pushed out to by its own weight.
+/// The body below it is searched outwards from the tile underneath
+/// for the closest tile with room in it that is lower than the point.
+fn pressed(world: &World, (x, y, z): Point3D) -> Option<Point3D> {
+    let start = (x, y, z - 1);
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(start);
+    queue.push_back(start);
+    while let Some(pnt) = queue.pop_front() {
+        if seen.len() > PRESSURE_REACH {
+            return None;
+        }
+        for next in around(pnt) {
+            if next.2 >= z || seen.contains(&next) {
+                continue;
+            }
+            match space_at(world, next) {
+                Some(FULL_DEPTH) => {
+                    seen.insert(next);
+                    queue.push_back(next);
+                }
+                Some(_) => return Some(next),
+                None => {}
+            }
+        }
+    }
+    None
+}
+
+/// The six points next to a point: below, above and on each side.
+fn around((x, y, z): Point3D) -> Vec<Point3D> {
+    let mut points = strict_adjacent((x, y))
+        .into_iter()
+        .filter(|&pos| pos != (x, y))
+        .map(|(ax, ay)| (ax, ay, z))
+        .collect::<Vec<_>>();
+    if z > 0 {
+        points.push((x, y, z - 1));
+    }
+    points.push((x, y, z + 1));
+    points
+}
+
+/// The liquid at a point, if there is any.
+fn liquid_at(world: &World,
+             pnt: Point3D)
+    -> Option<(LiquidPurity, Depth)> {
+    match world.tile_at(pnt) {
+        Some(Tile::Water(purity, State::Liquid, depth)) => {
+            Some((purity, depth))
+        }
+        _ => None,
+    }
+}
+
+fn is_lava(tile: Option<Tile>) -> bool {
+    matches!(tile, Some(Tile::Stone(_, State::Liquid)))
+}
+
+/// How much liquid there is at a point that liquid can flow into: 0
+/// for open air, or the depth of the liquid already there. None if
+/// the point is blocked, or off the map.
+fn space_at(world: &World, pnt: Point3D) -> Option<Depth> {
+    let unit = get!(world.unit_at((pnt.0, pnt.1)));
+    let tiles = unit.tiles.borrow();
+    match tiles.get(pnt.2) {
+        None | Some(&Tile::Empty) => Some(0),
+        Some(&Tile::Water(_, State::Liquid, depth)) => Some(depth),
+        _ => None,
+    }
+}
+
+/// Takes up to the given depth of water out of a point, as when it
+/// is drawn off into a vessel, lowering the tile or clearing it.
+pub fn draw_off(world: &World, pnt: Point3D, depth: Depth) {
+    if let Some(Tile::Water(purity, State::Liquid, held)) =
+        world.tile_at(pnt)
+    {
+        set_liquid(world, pnt, purity, held - cmp::min(held, depth));
+    }
+}
+
+/// Fills a point with the given depth of liquid, or empties it if the
+/// depth is 0.
+fn set_liquid(world: &World,
+              pnt: Point3D,
+              purity: LiquidPurity,
+              depth: Depth) {
+    let tile = if depth > 0 {
+        Tile::Water(purity, State::Liquid, depth)
+    } else {
+        Tile::Empty
+    };
+    set_tile(world, pnt, tile);
+}
+
+/// Puts a tile at a point. Columns grow to fit liquid above their
+/// top, and shrink again as it drains away.
+fn set_tile(world: &World, (x, y, z): Point3D, tile: Tile) {
+    let unit = match world.unit_at((x, y)) {
+        Some(unit) => unit,
+        None => return,
+    };
+    let mut tiles = unit.tiles.borrow_mut();
+    while tiles.len() <= z {
+        tiles.push(Tile::Empty);
+    }
+    tiles[z] = tile;
+    while tiles.last() == Some(&Tile::Empty) {
+        tiles.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rock(height: usize) -> Vec<Tile> {
+        let basalt = StoneTypes::Igneous(IgneousRocks::Basalt);
+        vec![Tile::Stone(basalt, State::Solid); height]
+    }
+
+    #[test]
+    fn water_running_over_rock_stays_as_dirty() {
+        let poisoned = Tile::Water(LiquidPurity::Toxic,
+                                   State::Liquid,
+                                   FULL_DEPTH);
+        let mut pool = rock(2);
+        pool.push(poisoned);
+        let mut ws = WorldState::new();
+        ws.map = Some(World::from_columns(vec![vec![pool, rock(2)]]));
+        run(&mut ws);
+        let world = ws.map.as_ref().unwrap();
+        assert_eq!(world.tile_at((1, 0, 2)),
+                   Some(Tile::Water(LiquidPurity::Toxic,
+                                    State::Liquid,
+                                    1)));
+    }
 }
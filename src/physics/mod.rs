@@ -1,62 +1,41 @@
-use utils::strict_adjacent;
-use worldgen::WorldState;
+use worldgen::{WorldEvent, WorldState};
 use worldgen::terrain::Tile;
 
+pub mod fire;
 pub mod liquid;
 pub mod stone;
 
 pub trait PhysicsActor {
     fn solid(&self) -> bool;
     fn heavy(&self) -> bool;
+    /// Whether it drops into the tile below it by itself. Loose
+    /// material falls through empty space, and through water too if
+    /// it is heavy enough to sink.
+    fn falls_into(&self, below: &Self) -> bool;
+    /// Whether it slides off a pile that is steeper than it can hold.
+    fn granular(&self) -> bool;
 }
 
+/// Whether a tile has lost what holds it up. It needs solid ground
+/// under it, unless at least two solid tiles at its sides hold it in
+/// place, or it is heavy and wedged in between something solid on top
+/// and at one side.
 fn unsupported(tile: Tile,
                adj: Vec<Tile>,
                above: Tile,
                below: Tile)
     -> bool {
     let solid_cnt = adj.iter()
-                       .take_while(|x| x.solid())
+                       .filter(|x| x.solid())
                        .count();
-    !below.solid() && (!above.solid() || !tile.heavy()) &&
-        solid_cnt < 2
+    let wedged = above.solid() && tile.heavy() && solid_cnt > 0;
+    !below.solid() && !wedged && solid_cnt < 2
 }
 
-pub fn run(ws: &mut WorldState, _dt: usize) {
-    if let Some(ref world) = ws.map {
-        let map = &world.map;
-        for y in 0..(world.map_size.1) {
-            for x in 0..(world.map_size.0) {
-                // TODO: Add or remove snow based on temperature.
-                let unit = &map[y][x];
-                let mut ut = unit.tiles.borrow_mut();
-                for h in 0..ut.len() {
-                    let tile = ut[h];
-
-                    let adj = strict_adjacent((x, y))
-                        .iter()
-                        .map(|pnt| {
-                            let unit = get!(world.get(*pnt));
-                            let tiles = unit.tiles.borrow();
-                            Some(tiles[h])
-                        })
-                        .filter(|x| x.is_some())
-                        .map(|x| x.unwrap())
-                        .collect::<Vec<_>>();
-                    if unsupported(tile,
-                                   adj,
-                                   *ut.get(h + 1)
-                                      .unwrap_or(&Tile::Empty),
-                                   *ut.get((h.checked_sub(1)
-                                             .unwrap_or(0)))
-                                      .unwrap_or(&Tile::Empty))
-                    {
-                        let tmp = ut[h];
-                        ut[h] = Tile::Empty;
-                        ut[h - 1] = tmp;
-                    }
-                }
-            }
-        }
-    }
+/// Moves everything on the map that moves by itself on by a tick.
+/// Returns what happened that the player should hear about.
+pub fn run(ws: &mut WorldState) -> Vec<WorldEvent> {
+    liquid::run(ws);
+    fire::run(ws);
+    stone::run(ws).into_iter().collect()
 }
@@ -1,13 +1,250 @@
-use std::rc::Rc;
-use worldgen::Unit;
-
-pub fn solid_physics(_pnt: (usize, usize),
-                     _aj: Vec<Rc<Unit>>)
-                     -> Option<Vec<Unit>> {
-    None
-}
-pub fn liquid_physics(_pnt: (usize, usize),
-                      _aj: Vec<Rc<Unit>>)
-                      -> Option<Vec<Unit>> {
-    None
+extern crate rand;
+
+use std::collections::BTreeSet;
+
+use self::rand::Rng;
+
+use physics::{PhysicsActor, unsupported};
+use physics::liquid::Flow;
+use utils::{Point3D, strict_adjacent};
+use worldgen::{World, WorldEvent, WorldState};
+use worldgen::terrain::*;
+
+/// The most tiles checked in a tick. A cave-in or a landslide that
+/// runs on for longer carries on the next tick.
+const CHECKS_PER_TICK: usize = 512;
+/// How much damage a falling block does to whoever it lands on.
+const CRUSH_DAMAGE: i32 = 150;
+
+/// The places where the map has changed, so that the stone and
+/// structures around them may have lost what held them up, and loose
+/// material may have room to fall. Only these are checked, rather
+/// than the whole map every tick.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Settling {
+    pending: BTreeSet<Point3D>,
+}
+
+impl Settling {
+    /// Marks a point as changed, so that it and the tiles around it
+    /// are checked on the next tick.
+    pub fn disturb(&mut self, (x, y, z): Point3D) {
+        self.pending.insert((x, y, z));
+        self.pending.insert((x, y, z + 1));
+        if z > 0 {
+            self.pending.insert((x, y, z - 1));
+        }
+        for (ax, ay) in strict_adjacent((x, y)) {
+            self.pending.insert((ax, ay, z));
+            self.pending.insert((ax, ay, z + 1));
+        }
+    }
+}
+
+/// Lets everything around the changes to the map settle under its
+/// own weight. Stone and constructions left with nothing holding them
+/// up come down as loose rubble, loose material and items fall until
+/// they land on something, and sand slides off any slope steeper
+/// than one tile down for every tile across. Whatever heavy falls
+/// crushes anyone in the way, and whatever moves disturbs what was
+/// around it, so a cave-in or landslide spreads as far as it has to.
+/// Returns a cave-in event if any stone came down, with where it
+/// started and how many blocks fell.
+pub fn run(ws: &mut WorldState) -> Option<WorldEvent> {
+    let settling = &mut ws.settling;
+    let flow = &mut ws.flow;
+    let rng = &mut ws.rng.physics;
+    let world = match ws.map {
+        Some(ref world) => world,
+        None => return None,
+    };
+    let mut fallen = 0;
+    let mut first = None;
+    for _ in 0..CHECKS_PER_TICK {
+        let pnt = match settling.pending.iter().next().cloned() {
+            Some(pnt) => pnt,
+            None => break,
+        };
+        settling.pending.remove(&pnt);
+        if let Some(landed) = collapse(world, pnt, flow) {
+            first = first.or(Some(pnt));
+            fallen += 1;
+            settling.disturb(pnt);
+            settling.disturb(landed);
+        } else if let Some(landed) = fall(world, pnt, flow, rng) {
+            settling.disturb(pnt);
+            settling.disturb(landed);
+        }
+    }
+    first.map(|pnt| WorldEvent::CaveIn(pnt, fallen))
+}
+
+/// Drops the tile at a point if it is a block of stone or a
+/// construction with nothing holding it up, and returns where it
+/// landed.
+fn collapse(world: &World, (x, y, z): Point3D, flow: &mut Flow)
+    -> Option<Point3D> {
+    if z == 0 {
+        // Nothing falls through the bottom of the world.
+        return None;
+    }
+    let tile = get!(world.tile_at((x, y, z)));
+    let rubble = get!(rubble(tile));
+    let adj = strict_adjacent((x, y))
+        .into_iter()
+        .filter(|&pos| pos != (x, y))
+        .filter_map(|(ax, ay)| world.tile_at((ax, ay, z)))
+        .collect::<Vec<_>>();
+    let above = world.tile_at((x, y, z + 1)).unwrap_or(Tile::Empty);
+    let below = world.tile_at((x, y, z - 1)).unwrap_or(Tile::Empty);
+    if !unsupported(tile, adj, above, below) {
+        return None;
+    }
+    world.place((x, y, z), rubble);
+    Some(drop_down(world, (x, y, z), flow))
+}
+
+/// Moves loose material or an item at a point down, or for sand
+/// that is piled too steeply, down the side of the pile. Returns
+/// where it came to rest, if it moved.
+fn fall<R: Rng>(world: &World,
+                (x, y, z): Point3D,
+                flow: &mut Flow,
+                rng: &mut R)
+    -> Option<Point3D> {
+    if z == 0 {
+        return None;
+    }
+    let tile = get!(world.tile_at((x, y, z)));
+    let below = get!(world.tile_at((x, y, z - 1)));
+    if tile.falls_into(&below) {
+        return Some(drop_down(world, (x, y, z), flow));
+    }
+    // Sand only slides off the top of a pile.
+    let above = world.tile_at((x, y, z + 1)).unwrap_or(Tile::Empty);
+    if !tile.granular() || above != Tile::Empty {
+        return None;
+    }
+    let loose = match tile {
+        Tile::Stone(kind, _) => {
+            Tile::Moveable(RestrictedTile::Stone(kind, State::Solid))
+        }
+        _ => tile,
+    };
+    let open = |pnt| world.tile_at(pnt).unwrap_or(Tile::Empty);
+    let sides = strict_adjacent((x, y))
+        .into_iter()
+        .filter(|&pos| pos != (x, y))
+        .filter(|&(ax, ay)| {
+                    world.unit_at((ax, ay)).is_some() &&
+                        open((ax, ay, z)) == Tile::Empty &&
+                        loose.falls_into(&open((ax, ay, z - 1)))
+                })
+        .collect::<Vec<_>>();
+    let (ax, ay) = *get!(rng.choose(&sides));
+    if let Some(unit) = world.unit_at((x, y)) {
+        let mut tiles = unit.tiles.borrow_mut();
+        tiles[z] = Tile::Empty;
+        while tiles.last() == Some(&Tile::Empty) {
+            tiles.pop();
+        }
+    }
+    flow.wake((x, y, z));
+    if let Some(unit) = world.unit_at((ax, ay)) {
+        let mut tiles = unit.tiles.borrow_mut();
+        while tiles.len() <= z {
+            tiles.push(Tile::Empty);
+        }
+        tiles[z] = loose;
+    }
+    Some(drop_down(world, (ax, ay, z), flow))
+}
+
+/// Lets the loose tile at a point fall through whatever it falls
+/// into, which is pushed up to fill the space it leaves behind.
+/// Anything heavy crushes anyone in the way. Returns where it landed.
+fn drop_down(world: &World, (x, y, z): Point3D, flow: &mut Flow)
+    -> Point3D {
+    let (tile, landed) = match world.unit_at((x, y)) {
+        Some(unit) => {
+            let mut tiles = unit.tiles.borrow_mut();
+            let tile = tiles[z];
+            let mut to = z;
+            while to > 0 && tile.falls_into(&tiles[to - 1]) {
+                to -= 1;
+            }
+            tiles.remove(z);
+            tiles.insert(to, tile);
+            (tile, to)
+        }
+        None => return (x, y, z),
+    };
+    for depth in landed..z + 1 {
+        flow.wake((x, y, depth));
+    }
+    if tile.heavy() {
+        for actor in &world.life {
+            let mut actor = actor.borrow_mut();
+            let (ax, ay, az) = actor.current_pos();
+            if (ax, ay) == (x, y) && az >= landed && az <= z {
+                actor.hurt(CRUSH_DAMAGE);
+            }
+        }
+    }
+    (x, y, landed)
+}
+
+/// What a falling block breaks into, if it is the kind that falls.
+fn rubble(tile: Tile) -> Option<Tile> {
+    let loose = match tile {
+        Tile::Stone(kind, State::Solid) |
+        Tile::Construction(Material::Stone(kind), _) => {
+            RestrictedTile::Stone(kind, State::Solid)
+        }
+        Tile::Construction(Material::Wood(vt), _) => {
+            RestrictedTile::Vegetation(vt, 1, State::Solid)
+        }
+        _ => return None,
+    };
+    Some(Tile::Moveable(loose))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASALT: StoneTypes =
+        StoneTypes::Igneous(IgneousRocks::Basalt);
+
+    fn rock(height: usize) -> Vec<Tile> {
+        vec![Tile::Stone(BASALT, State::Solid); height]
+    }
+
+    #[test]
+    fn mining_under_an_overhang_brings_it_down() {
+        // A wall along the west side, with one block sticking out of
+        // it over the middle column.
+        let mut ws = WorldState::new();
+        ws.map = Some(World::from_columns(vec![vec![rock(3),
+                                                    rock(2),
+                                                    rock(2)],
+                                               vec![rock(3),
+                                                    rock(3),
+                                                    rock(2)],
+                                               vec![rock(3),
+                                                    rock(2),
+                                                    rock(2)]]));
+        ws.settling.disturb((1, 1, 2));
+        assert_eq!(run(&mut ws), None);
+        ws.map.as_ref().unwrap().dig((1, 1, 1));
+        ws.settling.disturb((1, 1, 1));
+        assert_eq!(run(&mut ws),
+                   Some(WorldEvent::CaveIn((1, 1, 2), 1)));
+        let rubble = RestrictedTile::Stone(BASALT, State::Solid);
+        let world = ws.map.as_ref().unwrap();
+        assert_eq!(world.tile_at((1, 1, 2)),
+                   Some(Tile::Moveable(rubble)));
+        assert_eq!(world.tile_at((0, 1, 2)),
+                   Some(Tile::Stone(BASALT, State::Solid)));
+    }
 }
@@ -0,0 +1,56 @@
+extern crate rand;
+
+use self::rand::Rng;
+
+/// A small seedable random number generator (SplitMix64) whose whole
+/// state is one number, so it can be saved along with the game and
+/// pick up exactly where it left off.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stream {
+    state: u64,
+}
+
+impl Stream {
+    /// Creates the numbered stream for a seed. Streams of the same
+    /// seed but with different numbers do not overlap in practice.
+    pub fn new(seed: u32, stream: u32) -> Stream {
+        let mut s = Stream { state: (seed as u64) << 32 | stream as u64 };
+        // Mix once so that neighbouring seeds start far apart.
+        s.next_u64();
+        s
+    }
+}
+
+impl Rng for Stream {
+    fn next_u32(&mut self) -> u32 { (self.next_u64() >> 32) as u32 }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// All randomness in a running game, split up by subsystem. Each
+/// subsystem draws from its own stream, so that, for example, a
+/// change to how often the weather rolls does not shift what the
+/// animals decide. Together with the world seed and the player's
+/// input, this makes a game replay exactly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Streams {
+    pub weather: Stream,
+    pub life: Stream,
+    pub physics: Stream,
+}
+
+impl Streams {
+    pub fn new(seed: u32) -> Streams {
+        Streams {
+            weather: Stream::new(seed, 1),
+            life: Stream::new(seed, 2),
+            physics: Stream::new(seed, 3),
+        }
+    }
+}
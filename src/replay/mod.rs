@@ -0,0 +1,183 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json;
+
+use jobs;
+use life::Order;
+use save::SaveError;
+use utils::{Point2D, Rect2D};
+use worldgen::{World, WorldEvent, WorldState};
+
+/// The version of the replay format written by this build.
+pub const REPLAY_VERSION: u64 = 1;
+
+/// Where the game records the inputs of the last game started.
+pub const REPLAY_PATH: &'static str = "replays/last.jsonl";
+
+/// Something the player did that changes the world state.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Input {
+    /// A finished order, with its area filled in.
+    Order(Order),
+    /// Called off all work in an area.
+    Cancel(Rect2D),
+    /// Scrolled the map to a new screen offset.
+    Screen((i32, i32)),
+    /// Moved to a different level.
+    Level(i32),
+    Pause,
+    Resume,
+}
+
+impl Input {
+    /// Applies the input to the world state, as the game does when
+    /// the player gives it. Pausing and resuming only decide when the
+    /// world is updated, so they leave the state alone.
+    pub fn apply(&self, ws: &mut WorldState) {
+        match *self {
+            Input::Order(order) => ws.commands.push(order),
+            Input::Cancel(area) => jobs::cancel(ws, area),
+            Input::Screen(screen) => ws.screen = screen,
+            Input::Level(level) => ws.level = level,
+            Input::Pause | Input::Resume => {}
+        }
+    }
+}
+
+/// An input, tagged with the tick it came in on: it was given after
+/// `tick` updates of the world, before the next one.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub tick: usize,
+    pub input: Input,
+}
+
+/// The first line of a replay file.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u64,
+    seed: u32,
+}
+
+/// A recorded game: the seed its world was generated from and the
+/// player's inputs, in the order they were given.
+pub struct Replay {
+    pub seed: u32,
+    pub events: Vec<Event>,
+}
+
+/// Writes the player's inputs to a replay file as they happen, one
+/// JSON object per line, so that even a game that crashes leaves a
+/// usable replay behind.
+pub struct Recorder {
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    /// Starts a new replay file for a world made from `seed`,
+    /// replacing any old one at the same path.
+    pub fn create(path: &str,
+                  seed: u32)
+        -> Result<Recorder, SaveError> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut recorder =
+            Recorder { out: BufWriter::new(File::create(path)?) };
+        recorder.write_line(&Header {
+                                version: REPLAY_VERSION,
+                                seed: seed,
+                            })?;
+        Ok(recorder)
+    }
+
+    /// Adds an input given on the given tick to the replay.
+    pub fn record(&mut self,
+                  tick: usize,
+                  input: Input)
+        -> Result<(), SaveError> {
+        self.write_line(&Event {
+                            tick: tick,
+                            input: input,
+                        })
+    }
+
+    fn write_line<T: Serialize>(&mut self,
+                                value: &T)
+        -> Result<(), SaveError> {
+        serde_json::to_writer(&mut self.out, value)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads a replay file back.
+pub fn load(path: &str) -> Result<Replay, SaveError> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header: Header = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => return Err(SaveError::Version(0)),
+    };
+    if header.version != REPLAY_VERSION {
+        return Err(SaveError::Version(header.version));
+    }
+    let mut events = vec![];
+    for line in lines {
+        let line = line?;
+        if !line.trim().is_empty() {
+            events.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(Replay {
+           seed: header.seed,
+           events: events,
+       })
+}
+
+impl Replay {
+    /// Generates the replay's world and plays the inputs back into it
+    /// for the given number of ticks, with no one at the controls.
+    pub fn play(&self, size: Point2D, ticks: usize) -> WorldState {
+        let mut ws = WorldState::new();
+        ws.add_map(World::new(size, self.seed));
+        self.play_into(&mut ws, ticks);
+        ws
+    }
+
+    /// Steps an already generated world for the given number of
+    /// ticks, giving it each input when its tick comes up. Returns
+    /// the events of every tick.
+    pub fn play_into(&self,
+                     ws: &mut WorldState,
+                     ticks: usize)
+        -> Vec<WorldEvent> {
+        let (start, end) = (ws.tick, ws.tick + ticks);
+        let mut happened = vec![];
+        // Events are recorded in tick order, so the first one still
+        // to come can be looked up rather than searched for, which
+        // keeps stepping a replay one tick at a time cheap.
+        let first = match self.events.binary_search_by(|e| {
+            if e.tick < start {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        }) {
+            Ok(i) | Err(i) => i,
+        };
+        let mut events = self.events[first..].iter().peekable();
+        while ws.tick < end {
+            while events.peek().map_or(false, |e| e.tick <= ws.tick) {
+                events.next().unwrap().input.apply(ws);
+            }
+            happened.extend(ws.update());
+        }
+        happened
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use serde_json;
+use serde_json::Value;
+
+use random::Streams;
+use time::{Calendar, CalendarConfig};
+use worldgen::WorldState;
+use worldgen::terrain::FULL_DEPTH;
+
+/// The version of the save format written by this build. Bump it,
+/// and add a step to `MIGRATIONS`, whenever a change to the saved
+/// types would stop older saves from loading.
+pub const SAVE_VERSION: u64 = 3;
+
+/// Where the Save and Load menu entries keep the game.
+pub const SAVE_PATH: &'static str = "saves/world.json";
+
+/// Upgrades older saves one version at a time: the first step turns a
+/// version 1 save into a version 2 one, and so on, so there is always
+/// one step fewer than `SAVE_VERSION`. Steps work on the raw JSON, as
+/// the old format no longer has types to deserialise into.
+const MIGRATIONS: &'static [fn(Value) -> Value] = &[to_version_2,
+                                                   clamp_depths];
+
+/// Everything that can go wrong while saving or loading a game.
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    Version(u64),
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> SaveError { SaveError::Io(e) }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> SaveError {
+        SaveError::Format(e)
+    }
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &SaveError::Io(ref e) => write!(f, "{}", e),
+            &SaveError::Format(ref e) => {
+                write!(f, "malformed save: {}", e)
+            }
+            &SaveError::Version(v) => {
+                write!(f,
+                       "unsupported save version {} (expected {} or \
+                        older)",
+                       v,
+                       SAVE_VERSION)
+            }
+        }
+    }
+}
+
+/// The on-disk layout: the format version next to the world state.
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u64,
+    state: &'a WorldState,
+}
+
+/// Writes the whole world state to the given path, creating the
+/// directory if needed.
+pub fn save(ws: &WorldState, path: &str) -> Result<(), SaveError> {
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(file,
+                          &SaveFile {
+                              version: SAVE_VERSION,
+                              state: ws,
+                          })?;
+    Ok(())
+}
+
+/// Reads a world state back from the given path, migrating it from
+/// older save versions first.
+pub fn load(path: &str) -> Result<WorldState, SaveError> {
+    let file = BufReader::new(File::open(path)?);
+    let mut fields = match serde_json::from_reader(file)? {
+        Value::Object(fields) => fields,
+        _ => return Err(SaveError::Version(0)),
+    };
+    let version = fields.get("version")
+                        .and_then(Value::as_u64)
+                        .unwrap_or(0);
+    let state = fields.remove("state").unwrap_or(Value::Null);
+    let state = migrate(version, state)?;
+    Ok(serde_json::from_value(state)?)
+}
+
+/// Version 1 to 2: fills in the random streams, then fixes the date.
+fn to_version_2(state: Value) -> Value { redate(reseed(state)) }
+
+/// The first version 1 saves were made before the game kept its
+/// random streams, so they are given the ones a new game of the same
+/// world starts with.
+fn reseed(mut state: Value) -> Value {
+    if state.get("rng").is_some() {
+        return state;
+    }
+    let seed = state.pointer("/map/seed")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0);
+    if let (Some(fields), Ok(rng)) =
+        (state.as_object_mut(),
+         serde_json::to_value(Streams::new(seed as u32)))
+    {
+        fields.insert("rng".to_string(), rng);
+    }
+    state
+}
+
+/// The old calendar got the month and year wrong, so the date is
+/// worked out again from the count of days.
+fn redate(mut state: Value) -> Value {
+    let days = state.pointer("/time/days").and_then(Value::as_u64);
+    let config = state.pointer("/time/calendar/config")
+                      .cloned()
+                      .and_then(|c| serde_json::from_value(c).ok())
+                      .unwrap_or_else(CalendarConfig::default);
+    if let Some(days) = days {
+        let calendar = Calendar::new(config, days as usize);
+        if let (Some(slot), Ok(calendar)) =
+            (state.pointer_mut("/time/calendar"),
+             serde_json::to_value(calendar))
+        {
+            *slot = calendar;
+        }
+    }
+    state
+}
+
+/// Version 2 to 3: water used to be as deep as the rest of its column
+/// was tall, which could be anything, where it now holds between one
+/// and `FULL_DEPTH` levels.
+fn clamp_depths(mut state: Value) -> Value {
+    if let Some(rows) = state.pointer_mut("/map/map")
+                             .and_then(Value::as_array_mut) {
+        let tiles = rows.iter_mut()
+                        .filter_map(Value::as_array_mut)
+                        .flat_map(|row| row.iter_mut())
+                        .filter_map(|unit| unit.get_mut("tiles"))
+                        .filter_map(Value::as_array_mut)
+                        .flat_map(|tiles| tiles.iter_mut());
+        for tile in tiles {
+            let depth = tile.get_mut("Water")
+                            .and_then(Value::as_array_mut)
+                            .and_then(|fields| fields.get_mut(2));
+            if let Some(depth) = depth {
+                let old = depth.as_i64().unwrap_or(1);
+                let clamped = old.max(1).min(FULL_DEPTH as i64);
+                *depth = Value::from(clamped);
+            }
+        }
+    }
+    state
+}
+
+/// Runs every migration step from the given version up to the
+/// current one.
+fn migrate(version: u64, state: Value) -> Result<Value, SaveError> {
+    if version == 0 || version > SAVE_VERSION {
+        return Err(SaveError::Version(version));
+    }
+    Ok(MIGRATIONS[version as usize - 1..]
+           .iter()
+           .fold(state, |state, step| step(state)))
+}
@@ -2,9 +2,19 @@ extern crate rand;
 
 use self::rand::Rng;
 use draw::Describe;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-#[derive(PartialEq)]
+/// How many times a second the world is updated at normal speed.
+pub const TICKS_PER_SECOND: u32 = 10;
+/// How many minutes of game time pass with each tick.
+pub const MINUTES_PER_TICK: usize = 1;
+/// How many minutes there are in a day.
+pub const MINUTES_PER_DAY: usize = 24 * 60;
+/// The most ticks run to catch up in one frame. If the game falls
+/// further behind than this, it slows down instead of stalling.
+const MAX_TICKS_PER_FRAME: usize = 10;
+
+#[derive(PartialEq, Serialize, Deserialize)]
 pub enum Time {
     Night2,
     Morning,
@@ -15,19 +25,13 @@ pub enum Time {
 }
 impl Time {
     pub fn from_clock_time(clock: &Clock) -> Self {
-        let hours = clock.time.0;
-        if hours == 0 {
-            Time::Midnight
-        } else if hours > 0 && hours <= 7 {
-            Time::Night2
-        } else if hours < 12 {
-            Time::Morning
-        } else if hours <= 1 {
-            Time::Noon
-        } else if hours <= 6 {
-            Time::Afternoon
-        } else {
-            Time::Night1
+        match clock.time.0 {
+            0 => Time::Midnight,
+            1...5 => Time::Night2,
+            6...11 => Time::Morning,
+            12 => Time::Noon,
+            13...17 => Time::Afternoon,
+            _ => Time::Night1,
         }
     }
 }
@@ -44,7 +48,7 @@ impl Describe for Time {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Season {
     Autumn,
     Winter,
@@ -53,20 +57,20 @@ pub enum Season {
 }
 
 impl Season {
-    pub fn from_month(month: usize) -> Self {
-        if 3 <= month && month <= 5 {
-            Season::Spring
-        } else if 6 <= month && month <= 8 {
-            Season::Summer
-        } else if 9 <= month && month <= 11 {
-            Season::Autumn
-        } else {
-            Season::Winter
+    /// The season of a month, counting from 1. The year is split
+    /// into four seasons of (about) the same length, with winter
+    /// starting in the last month of the year.
+    pub fn from_month(month: usize, months_per_year: usize) -> Self {
+        match (month % months_per_year) * 4 / months_per_year {
+            0 => Season::Winter,
+            1 => Season::Spring,
+            2 => Season::Summer,
+            _ => Season::Autumn,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Weather {
     Raining,
     Snowing,
@@ -75,97 +79,324 @@ pub enum Weather {
 }
 
 impl Weather {
-    pub fn from_season_time(season: Season, hours: usize) -> Weather {
+    pub fn from_season_time<R: Rng>(season: Season,
+                                    hours: usize,
+                                    rng: &mut R)
+        -> Weather {
         match season {
             Season::Autumn => {
-                rand::thread_rng()
-                    .choose(&[Weather::Sunny, Weather::Overcast])
-                    .unwrap()
-                    .clone()
+                rng.choose(&[Weather::Sunny, Weather::Overcast])
+                   .unwrap()
+                   .clone()
             }
             Season::Winter => {
-                rand::thread_rng()
-                    .choose(&[Weather::Snowing, Weather::Overcast])
-                    .unwrap()
-                    .clone()
+                rng.choose(&[Weather::Snowing, Weather::Overcast])
+                   .unwrap()
+                   .clone()
             }
             Season::Spring => {
-                rand::thread_rng()
-                    .choose(&[Weather::Sunny, Weather::Raining])
-                    .unwrap()
-                    .clone()
+                rng.choose(&[Weather::Sunny, Weather::Raining])
+                   .unwrap()
+                   .clone()
             }
             Season::Summer => {
-                rand::thread_rng()
-                    .choose(&[Weather::Sunny,
-                              Weather::Raining,
-                              Weather::Raining])
-                    .unwrap()
-                    .clone()
+                rng.choose(&[Weather::Sunny,
+                             Weather::Raining,
+                             Weather::Raining])
+                   .unwrap()
+                   .clone()
             }
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+/// The shape of the year: what the months are called, which also
+/// sets how many there are, and how long each of them is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalendarConfig {
+    pub month_names: Vec<String>,
+    pub days_per_month: usize,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> CalendarConfig {
+        CalendarConfig {
+            month_names: ["January",
+                          "February",
+                          "March",
+                          "April",
+                          "May",
+                          "June",
+                          "July",
+                          "August",
+                          "September",
+                          "October",
+                          "November",
+                          "December"]
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+            days_per_month: 30,
+        }
+    }
+}
+
+impl CalendarConfig {
+    pub fn months_per_year(&self) -> usize { self.month_names.len() }
+
+    pub fn days_per_year(&self) -> usize {
+        self.days_per_month * self.months_per_year()
+    }
+
+    /// The day, month (both counting from 1) and year of the given
+    /// number of days since the start of year 0.
+    pub fn date(&self, days: usize) -> (usize, usize, usize) {
+        (days % self.days_per_month + 1,
+         days / self.days_per_month % self.months_per_year() + 1,
+         days / self.days_per_year())
+    }
+
+    /// The number of days since the start of year 0 of a day, month
+    /// and year, the opposite of `date`.
+    pub fn days(&self, (d, m, y): (usize, usize, usize)) -> usize {
+        let months = y * self.months_per_year() + m - 1;
+        months * self.days_per_month + d - 1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Calendar {
     pub dmy: (usize, usize, usize),
     pub season: Season,
-    pub weather: Weather,
+    #[serde(default)]
+    pub config: CalendarConfig,
 }
 
 impl Describe for Calendar {
     fn describe(&self) -> String {
-        format!("{:?}, {}/{}/{}",
+        // A month the year does not have is shown by its number.
+        let month = self.dmy
+                        .1
+                        .checked_sub(1)
+                        .and_then(|m| self.config.month_names.get(m))
+                        .cloned()
+                        .unwrap_or_else(|| self.dmy.1.to_string());
+        format!("{:?}, {} {} {}",
                 self.season,
                 self.dmy.0,
-                self.dmy.1,
+                month,
                 self.dmy.2)
     }
 }
 
 impl Calendar {
-    pub fn new(d: usize, m: usize, y: usize, clock: &Clock) -> Self {
+    /// Creates a calendar showing the given number of days since the
+    /// start of year 0.
+    pub fn new(config: CalendarConfig, days: usize) -> Self {
+        let dmy = config.date(days);
+        let season = Season::from_month(dmy.1,
+                                        config.months_per_year());
         Calendar {
-            dmy: (d, m, y),
-            season: Season::from_month(m),
-            weather: Weather::from_season_time(Season::from_month(m),
-                                               clock.time.0),
+            dmy: dmy,
+            season: season,
+            config: config,
         }
     }
-    pub fn update_to_day(&mut self, days: usize, clock: &Clock) {
-        self.dmy = (days % 30, days / 30, days / 356);
-        self.season = Season::from_month(self.dmy.1);
-        self.weather =
-            Weather::from_season_time(Season::from_month(self.dmy.1),
-                                      clock.time.0);
+    pub fn update_to_day(&mut self, days: usize) {
+        self.dmy = self.config.date(days);
+        let months = self.config.months_per_year();
+        self.season = Season::from_month(self.dmy.1, months);
     }
 }
 
+/// The time of day, as hours and minutes.
+#[derive(Serialize, Deserialize)]
 pub struct Clock {
     pub time: (usize, usize),
 }
 
 impl Describe for Clock {
     fn describe(&self) -> String {
-        format!("{}:{}", self.time.0, self.time.1)
+        format!("{}:{:02}", self.time.0, self.time.1)
     }
 }
 
 impl Clock {
-    pub fn update_deltatime(&mut self, dt: usize) {
-        let mins = self.time.1 + dt;
-        if mins >= 60 {
-            self.time = (self.time.0 + 1, 0);
-        } else {
-            self.time = (self.time.0, mins);
-        }
+    /// Moves the clock on by some minutes, as many hours and days as
+    /// that takes. Returns how many times it went past midnight.
+    pub fn update_deltatime(&mut self, dt: usize) -> usize {
+        let minutes = self.time.0 * 60 + self.time.1 + dt;
+        let since_midnight = minutes % MINUTES_PER_DAY;
+        self.time = (since_midnight / 60, since_midnight % 60);
+        minutes / MINUTES_PER_DAY
     }
 }
 
+/// How many whole days, and how far into the last one, the given
+/// number of ticks is.
+pub fn ticks_to_time(ticks: usize) -> (usize, (usize, usize)) {
+    let minutes = ticks * MINUTES_PER_TICK;
+    let since_midnight = minutes % MINUTES_PER_DAY;
+    (minutes / MINUTES_PER_DAY,
+     (since_midnight / 60, since_midnight % 60))
+}
+
+/// How many ticks it takes for the given days, hours and minutes to
+/// pass, rounded down to whole ticks.
+pub fn time_to_ticks(days: usize,
+                     (hours, minutes): (usize, usize))
+    -> usize {
+    (days * MINUTES_PER_DAY + hours * 60 + minutes) / MINUTES_PER_TICK
+}
+
 pub fn get_world_time() -> usize {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs() as usize
 }
+
+/// Paces the simulation at a fixed number of ticks per second,
+/// however often frames are drawn. Real time that has passed is
+/// collected each frame and paid out in whole ticks; the remainder
+/// carries over to the next frame.
+pub struct Ticker {
+    tick_length: Duration,
+    accumulator: Duration,
+    last: Instant,
+}
+
+impl Ticker {
+    pub fn new(ticks_per_second: u32) -> Ticker {
+        Ticker {
+            tick_length: Duration::new(0, 1_000_000_000 /
+                                              ticks_per_second),
+            accumulator: Duration::new(0, 0),
+            last: Instant::now(),
+        }
+    }
+
+    /// Forgets the time since the last frame, so that time spent in
+    /// menus is not caught up on afterwards.
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::new(0, 0);
+        self.last = Instant::now();
+    }
+
+    /// Returns how many ticks are due since the last call.
+    pub fn due(&mut self) -> usize {
+        let now = Instant::now();
+        self.accumulator += now.duration_since(self.last);
+        self.last = now;
+        let mut ticks = 0;
+        while self.accumulator >= self.tick_length {
+            self.accumulator -= self.tick_length;
+            ticks += 1;
+            if ticks == MAX_TICKS_PER_FRAME {
+                self.accumulator = Duration::new(0, 0);
+            }
+        }
+        ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn short_year() -> CalendarConfig {
+        CalendarConfig {
+            month_names: vec!["Thaw".to_string(),
+                              "Sun".to_string(),
+                              "Frost".to_string()],
+            days_per_month: 10,
+        }
+    }
+
+    #[test]
+    fn clock_rolls_over_hours_and_days() {
+        let mut clock = Clock { time: (10, 50) };
+        assert_eq!(clock.update_deltatime(130), 0);
+        assert_eq!(clock.time, (13, 0));
+        assert_eq!(clock.update_deltatime(11 * 60 + 5), 1);
+        assert_eq!(clock.time, (0, 5));
+        assert_eq!(clock.update_deltatime(3 * MINUTES_PER_DAY), 3);
+        assert_eq!(clock.time, (0, 5));
+    }
+
+    #[test]
+    fn time_of_day_covers_every_hour() {
+        let at = |h| Time::from_clock_time(&Clock { time: (h, 0) });
+        assert!(at(0) == Time::Midnight);
+        assert!(at(3) == Time::Night2);
+        assert!(at(8) == Time::Morning);
+        assert!(at(12) == Time::Noon);
+        assert!(at(15) == Time::Afternoon);
+        assert!(at(21) == Time::Night1);
+    }
+
+    #[test]
+    fn dates_wrap_months_and_years() {
+        let config = CalendarConfig::default();
+        assert_eq!(config.date(0), (1, 1, 0));
+        assert_eq!(config.date(29), (30, 1, 0));
+        assert_eq!(config.date(30), (1, 2, 0));
+        assert_eq!(config.date(359), (30, 12, 0));
+        assert_eq!(config.date(360), (1, 1, 1));
+        assert_eq!(config.date(36000 + 5 * 30 + 11), (12, 6, 100));
+    }
+
+    #[test]
+    fn dates_follow_the_configured_year() {
+        let config = short_year();
+        assert_eq!(config.days_per_year(), 30);
+        assert_eq!(config.date(25), (6, 3, 0));
+        assert_eq!(config.date(30), (1, 1, 1));
+    }
+
+    #[test]
+    fn days_and_dates_convert_both_ways() {
+        for config in vec![CalendarConfig::default(), short_year()] {
+            for days in 0..1000 {
+                assert_eq!(config.days(config.date(days)), days);
+            }
+        }
+    }
+
+    #[test]
+    fn seasons_split_the_year() {
+        assert_eq!(Season::from_month(12, 12), Season::Winter);
+        assert_eq!(Season::from_month(2, 12), Season::Winter);
+        assert_eq!(Season::from_month(3, 12), Season::Spring);
+        assert_eq!(Season::from_month(7, 12), Season::Summer);
+        assert_eq!(Season::from_month(11, 12), Season::Autumn);
+        assert_eq!(Season::from_month(1, 4), Season::Spring);
+        assert_eq!(Season::from_month(4, 4), Season::Winter);
+    }
+
+    #[test]
+    fn ticks_convert_to_days_and_back() {
+        let day = time_to_ticks(1, (0, 0));
+        assert_eq!(day, MINUTES_PER_DAY / MINUTES_PER_TICK);
+        assert_eq!(ticks_to_time(day), (1, (0, 0)));
+        let ticks = time_to_ticks(40, (13, 30));
+        assert_eq!(ticks_to_time(ticks), (40, (13, 30)));
+    }
+
+    #[test]
+    fn calendar_names_the_month() {
+        let mut calendar = Calendar::new(short_year(), 0);
+        calendar.update_to_day(14);
+        assert_eq!(calendar.dmy, (5, 2, 0));
+        assert_eq!(calendar.describe(), "Summer, 5 Sun 0");
+    }
+
+    #[test]
+    fn calendar_copes_with_months_out_of_range() {
+        let mut calendar = Calendar::new(short_year(), 0);
+        calendar.dmy = (5, 0, 0);
+        assert_eq!(calendar.describe(), "Spring, 5 0 0");
+        calendar.dmy = (5, 1217, 0);
+        assert_eq!(calendar.describe(), "Spring, 5 1217 0");
+    }
+}
@@ -2,9 +2,6 @@ extern crate rand;
 
 use std;
 use std::cmp;
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
-use std::collections::HashMap;
 
 use life;
 use worldgen::World;
@@ -34,6 +31,32 @@ pub fn weak_adjacent(p: Point2D) -> Vec<Point2D> {
     v
 }
 
+/// Returns every point inside a rectangle, including its edges. The
+/// corners can be given in any order.
+pub fn rect_points(((x1, y1), (x2, y2)): Rect2D) -> Vec<Point2D> {
+    let (sx, ex) = (cmp::min(x1, x2), cmp::max(x1, x2));
+    let (sy, ey) = (cmp::min(y1, y2), cmp::max(y1, y2));
+    (sy..ey + 1)
+        .flat_map(|y| (sx..ex + 1).map(move |x| (x, y)))
+        .collect()
+}
+
+/// Tests if a point is inside a rectangle, including its edges.
+pub fn in_rect((x, y): Point2D, ((x1, y1), (x2, y2)): Rect2D) -> bool {
+    x >= cmp::min(x1, x2) && x <= cmp::max(x1, x2) &&
+        y >= cmp::min(y1, y2) && y <= cmp::max(y1, y2)
+}
+
+/// Tests if two rectangles share at least one point.
+pub fn rects_overlap(((ax1, ay1), (ax2, ay2)): Rect2D,
+                     ((bx1, by1), (bx2, by2)): Rect2D)
+    -> bool {
+    cmp::min(ax1, ax2) <= cmp::max(bx1, bx2) &&
+        cmp::min(bx1, bx2) <= cmp::max(ax1, ax2) &&
+        cmp::min(ay1, ay2) <= cmp::max(by1, by2) &&
+        cmp::min(by1, by2) <= cmp::max(ay1, ay2)
+}
+
 pub fn distance((x1, y1): Point2D, (x2, y2): Point2D) -> f32 {
     (((x2 as i32 - x1 as i32).pow(2) +
           (y2 as i32 - y1 as i32).pow(2)) as f32)
@@ -80,7 +103,7 @@ pub fn distance3_d((x1, y1, z1): Point3D,
     let y2 = y2 as isize;
     let z2 = z2 as isize;
     (((x2 - x1).pow(2) + (y2 - y1).pow(2) + (z2 - z1).pow(2)) as f32)
-        .cbrt()
+        .sqrt()
 }
 
 pub fn can_move<'a>(map: &'a World,
@@ -105,13 +128,13 @@ pub fn can_move<'a>(map: &'a World,
     }
 }
 
-pub fn random_point(min_x: usize,
-                    max_x: usize,
-                    min_y: usize,
-                    max_y: usize)
+pub fn random_point<R: Rng>(min_x: usize,
+                            max_x: usize,
+                            min_y: usize,
+                            max_y: usize,
+                            rng: &mut R)
     -> Point2D {
-    let mut trng = rand::thread_rng();
-    (trng.gen_range(min_x, max_x), trng.gen_range(min_y, max_y))
+    (rng.gen_range(min_x, max_x), rng.gen_range(min_y, max_y))
 }
 
 pub fn strict_3d_adjacent(pos: Point3D, map: &World) -> Vec<Point3D> {
@@ -131,76 +154,3 @@ pub fn strict_3d_adjacent(pos: Point3D, map: &World) -> Vec<Point3D> {
         })
         .collect()
 }
-
-#[derive(Copy, Clone, Eq, PartialEq)]
-struct State {
-    cost: isize,
-    pos: Point3D,
-}
-
-impl Ord for State {
-    fn cmp(&self, other: &State) -> Ordering {
-        other.cost.cmp(&self.cost)
-    }
-}
-
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &State) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-/// Greedy best-first pathfinding search. Works on 3D points, but
-/// treats them as just levels of a 2D plane, not full 3D. This means
-/// that when it goes from one tile to the next, it chooses the
-/// closest empty tile on the second one. I chose GBFS because it is
-/// fast and simple, and finding the absolute best path is not a goal.
-pub fn find_path<'a>(map: &World,
-                     start: Point3D,
-                     goal: Point3D,
-                     can_move: Box<Fn(Point3D) -> bool + 'a>)
-    -> Option<Vec<Point3D>> {
-    let mut frontier = BinaryHeap::new();
-    frontier.push(State {
-                      cost: 0,
-                      pos: start,
-                  });
-
-    let mut came_from = HashMap::new();
-    came_from.insert(start, None);
-
-    while frontier.len() != 0 {
-        let current = frontier.pop();
-        if current.unwrap().pos == goal {
-            break;
-        }
-        for next in strict_3d_adjacent(current.unwrap().pos, map) {
-            if !came_from.contains_key(&next) && can_move(next) {
-                frontier.push(State {
-                                  pos: next,
-                                  cost: distance3_d(goal, next) as
-                                      isize,
-                              });
-                came_from.insert(next, current.map(|a| a.pos));
-            }
-        }
-    }
-
-    let mut current = goal;
-    let mut path = vec![current];
-    while current != start {
-        if let Some(c) = came_from.get(&current) {
-            if let Some(c) = *c {
-                current = c;
-                path.push(current);
-            } else {
-                return None;
-            }
-        } else {
-            return None;
-        }
-    }
-    path.push(start);
-    path.reverse();
-    Some(path)
-}
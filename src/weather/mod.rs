@@ -0,0 +1,342 @@
+extern crate rand;
+
+use std::f32::consts::PI;
+
+use self::rand::Rng;
+
+use draw::Describe;
+use time::{Clock, MINUTES_PER_DAY, Season, Weather};
+use worldgen::{World, WorldState};
+use worldgen::terrain::{Biome, Compass, FULL_DEPTH, Ferenheight,
+                        LiquidPurity, State, Tile};
+
+/// Water freezes and snow stays at or below this temperature.
+pub const FREEZING: Ferenheight = 32.0;
+/// Below this, even rain comes down as snow.
+const SNOW_LINE: Ferenheight = 28.0;
+/// How many hours a front can last, at least and at most.
+const FRONT_HOURS: (usize, usize) = (6, 72);
+/// The chance, each sunny hour, that a puddle dries a little.
+const DRY_CHANCE: f32 = 0.25;
+/// How many degrees from freezing it takes for water to be sure to
+/// freeze, or ice and snow to be sure to melt, within the hour.
+const THAW_RANGE: Ferenheight = 20.0;
+/// The thickest, in tiles, that ice grows over open water.
+const MAX_ICE: usize = 2;
+
+/// The weather over the whole map for a while: what kind it is, how
+/// hard it comes down and how many more hours it will last.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Front {
+    pub weather: Weather,
+    /// From 0 to 1, how heavy the rain or snow is, or how cold the
+    /// clouds keep it.
+    pub intensity: f32,
+    /// Where the wind blows from.
+    pub wind: Compass,
+    pub hours_left: usize,
+}
+
+impl Default for Front {
+    /// A clear sky that gives way to a real front the next hour.
+    fn default() -> Front {
+        Front {
+            weather: Weather::Sunny,
+            intensity: 0.0,
+            wind: Compass::North,
+            hours_left: 0,
+        }
+    }
+}
+
+impl Describe for Front {
+    fn describe(&self) -> String {
+        let strength = if self.intensity < 0.4 {
+            "light "
+        } else if self.intensity > 0.75 {
+            "heavy "
+        } else {
+            ""
+        };
+        let sky = match self.weather {
+            Weather::Sunny => "sunny".to_string(),
+            Weather::Overcast => "overcast".to_string(),
+            Weather::Raining => format!("{}rain", strength),
+            Weather::Snowing => format!("{}snow", strength),
+        };
+        format!("{}, {} wind", sky, self.wind.describe())
+    }
+}
+
+impl Front {
+    /// Rolls the next front for the season and hour.
+    pub fn new<R: Rng>(season: Season,
+                       hour: usize,
+                       rng: &mut R)
+        -> Front {
+        let winds = [Compass::North,
+                     Compass::South,
+                     Compass::East,
+                     Compass::West];
+        Front {
+            weather: Weather::from_season_time(season, hour, rng),
+            intensity: rng.gen_range(0.2, 1.0),
+            wind: *rng.choose(&winds).unwrap(),
+            hours_left: rng.gen_range(FRONT_HOURS.0, FRONT_HOURS.1),
+        }
+    }
+}
+
+/// What a front drops on the ground in a place, once the temperature
+/// there is taken into account.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Fall {
+    Rain,
+    /// Rain that freezes where it lands.
+    Glaze,
+    Snow,
+}
+
+impl Fall {
+    fn of(front: &Front, temp: Ferenheight) -> Option<Fall> {
+        let snowing = front.weather == Weather::Snowing;
+        if front.weather != Weather::Raining && !snowing {
+            None
+        } else if temp <= SNOW_LINE || (snowing && temp <= FREEZING) {
+            Some(Fall::Snow)
+        } else if temp <= FREEZING {
+            Some(Fall::Glaze)
+        } else {
+            Some(Fall::Rain)
+        }
+    }
+}
+
+/// How warm it is in a biome right now. The day follows a curve
+/// between the biome's night and day temperatures, coldest at three
+/// in the morning and warmest at three in the afternoon, and the
+/// season and the clouds of the front shift the whole curve.
+pub fn temperature(biome: &Biome,
+                   season: Season,
+                   clock: &Clock,
+                   front: &Front)
+    -> Ferenheight {
+    let minutes = (clock.time.0 * 60 + clock.time.1) as f32;
+    let turn = (minutes - 180.0) / MINUTES_PER_DAY as f32;
+    let warmth = (1.0 - (turn * 2.0 * PI).cos()) / 2.0;
+    let season_offset = match season {
+        Season::Winter => -25.0,
+        Season::Spring => 0.0,
+        Season::Summer => 10.0,
+        Season::Autumn => -5.0,
+    };
+    let front_offset = match front.weather {
+        Weather::Sunny => 0.0,
+        Weather::Overcast => -3.0,
+        Weather::Raining => -5.0,
+        Weather::Snowing => -10.0,
+    } * front.intensity;
+    let (night, day) = (biome.temperature_night_f,
+                        biome.temperature_day_f);
+    night + (day - night) * warmth + season_offset + front_offset
+}
+
+/// Moves the weather on by an hour: the front wears on or gives way
+/// to the next one, and whatever it drops lands on the map. Rain
+/// gathers in puddles, freezing rain glazes the ground with ice and
+/// snow builds up, all only on open ground and on top of each other,
+/// never over plants or items. Sun dries puddles out again. Then the
+/// surface of each column freezes or thaws with the temperature.
+pub fn run(ws: &mut WorldState) {
+    let season = ws.time.calendar.season;
+    if ws.weather.hours_left == 0 {
+        ws.weather = Front::new(season,
+                                ws.time.clock.time.0,
+                                &mut ws.rng.weather);
+    } else {
+        ws.weather.hours_left -= 1;
+    }
+    let front = ws.weather;
+    let rng = &mut ws.rng.weather;
+    let flow = &mut ws.flow;
+    if let Some(ref world) = ws.map {
+        for (y, row) in world.map.iter().enumerate() {
+            for (x, unit) in row.iter().enumerate() {
+                let biome = match unit.biome {
+                    Some(b) => b,
+                    None => continue,
+                };
+                let temp = temperature(&biome,
+                                       season,
+                                       &ws.time.clock,
+                                       &front);
+                let mut tiles = unit.tiles.borrow_mut();
+                let top = top_tile(&tiles);
+                settle(&mut tiles, &front, &biome, temp, rng);
+                // Puddles, ice and snow coming and going can set
+                // water flowing.
+                let new_top = top_tile(&tiles);
+                if new_top != top {
+                    for &(z, _) in top.iter().chain(new_top.iter()) {
+                        flow.wake((x, y, z));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An hour of weather on one column of the map, at the given
+/// temperature.
+fn settle<R: Rng>(tiles: &mut Vec<Tile>,
+                  front: &Front,
+                  biome: &Biome,
+                  temp: Ferenheight,
+                  rng: &mut R) {
+    let chance = biome.percipitation_chance / 100.0 * front.intensity;
+    match Fall::of(front, temp) {
+        Some(fall) => {
+            if rng.gen::<f32>() < chance {
+                land(tiles, fall);
+            }
+        }
+        None => {
+            if front.weather == Weather::Sunny &&
+               rng.gen::<f32>() < DRY_CHANCE
+            {
+                dry(tiles);
+            }
+        }
+    }
+    freeze_or_thaw(tiles, temp, rng);
+}
+
+/// The index of the highest tile in a column that isn't empty.
+fn surface(tiles: &[Tile]) -> Option<usize> {
+    tiles.iter().rposition(|t| *t != Tile::Empty)
+}
+
+fn top_tile(tiles: &[Tile]) -> Option<(usize, Tile)> {
+    surface(tiles).map(|z| (z, tiles[z]))
+}
+
+fn land(tiles: &mut Vec<Tile>, fall: Fall) {
+    let z = match surface(tiles) {
+        Some(z) => z,
+        None => return,
+    };
+    let cover = match (tiles[z], fall) {
+        (Tile::Snow(d), Fall::Snow) => {
+            tiles[z] = Tile::Snow((d + 1).min(FULL_DEPTH));
+            return;
+        }
+        (Tile::Water(p, State::Liquid, d), Fall::Rain) => {
+            // Rain is lost in lakes and the sea, and only fills up
+            // puddles lying on the ground.
+            if z == 0 || !World::is_water(tiles[z - 1]) {
+                let depth = (d + 1).min(FULL_DEPTH);
+                tiles[z] = Tile::Water(p, State::Liquid, depth);
+            }
+            return;
+        }
+        (Tile::Stone(_, State::Solid), _) |
+        (Tile::Construction(..), _) |
+        (Tile::Water(_, State::Solid, _), Fall::Rain) |
+        (Tile::Water(_, State::Solid, _), Fall::Snow) => {
+            match fall {
+                Fall::Rain => {
+                    Tile::Water(LiquidPurity::Clean, State::Liquid, 1)
+                }
+                Fall::Glaze => {
+                    Tile::Water(LiquidPurity::Clean, State::Solid, 1)
+                }
+                Fall::Snow => Tile::Snow(1),
+            }
+        }
+        // Snow falling into water melts, rain on snow soaks in, and
+        // nothing settles on plants, items or rubble.
+        _ => return,
+    };
+    if z + 1 < tiles.len() {
+        tiles[z + 1] = cover;
+    } else {
+        tiles.push(cover);
+    }
+}
+
+fn dry(tiles: &mut Vec<Tile>) {
+    let z = match surface(tiles) {
+        Some(z) => z,
+        None => return,
+    };
+    match tiles[z] {
+        Tile::Water(p, State::Liquid, d) if d > 1 &&
+                                            d < FULL_DEPTH => {
+            tiles[z] = Tile::Water(p, State::Liquid, d - 1);
+        }
+        Tile::Water(_, State::Liquid, 1) => {
+            if z + 1 == tiles.len() {
+                tiles.pop();
+            } else {
+                tiles[z] = Tile::Empty;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Freezes or thaws the top of a column, the warmer or colder it is
+/// the likelier. In the cold, still water ices over from the surface
+/// down, up to `MAX_ICE` tiles thick, and the deeper the water under
+/// the ice the slower, since it holds more heat. In the warmth, snow
+/// melts a layer at a time, leaving a puddle once it is gone, and ice
+/// turns back into water. Only what is on top feels the air, so snow
+/// keeps the ice under it frozen.
+fn freeze_or_thaw<R: Rng>(tiles: &mut Vec<Tile>,
+                          temp: Ferenheight,
+                          rng: &mut R) {
+    let z = match surface(tiles) {
+        Some(z) => z,
+        None => return,
+    };
+    let chance = (temp - FREEZING).abs() / THAW_RANGE;
+    let roll = rng.gen::<f32>();
+    if temp <= FREEZING {
+        let ice = tiles[..z + 1]
+            .iter()
+            .rev()
+            .take_while(|t| is_water(**t, State::Solid))
+            .count();
+        if ice >= MAX_ICE || ice > z {
+            return;
+        }
+        let depth = tiles[..z + 1 - ice]
+            .iter()
+            .rev()
+            .take_while(|t| is_water(**t, State::Liquid))
+            .count();
+        if let Tile::Water(p, State::Liquid, d) = tiles[z - ice] {
+            if roll < chance / depth as f32 {
+                tiles[z - ice] = Tile::Water(p, State::Solid, d);
+            }
+        }
+    } else if roll < chance {
+        tiles[z] = match tiles[z] {
+            Tile::Snow(d) if d > 1 => Tile::Snow(d - 1),
+            Tile::Snow(_) => {
+                Tile::Water(LiquidPurity::Clean, State::Liquid, 1)
+            }
+            Tile::Water(p, State::Solid, d) => {
+                Tile::Water(p, State::Liquid, d)
+            }
+            t => t,
+        };
+    }
+}
+
+fn is_water(tile: Tile, state: State) -> bool {
+    match tile {
+        Tile::Water(_, s, _) => s == state,
+        _ => false,
+    }
+}
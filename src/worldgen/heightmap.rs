@@ -0,0 +1,197 @@
+extern crate rand;
+
+use self::rand::Rng;
+
+use std;
+
+use utils::Point2D;
+use worldgen::noise::Noise;
+
+/// The eight neighbours of a cell, as offsets.
+const NEIGHBOURS: [(isize, isize); 8] = [(-1, -1),
+                                         (0, -1),
+                                         (1, -1),
+                                         (-1, 0),
+                                         (1, 0),
+                                         (-1, 1),
+                                         (0, 1),
+                                         (1, 1)];
+
+/// A grid of heights that the terrain is carved out of. It supports
+/// the same handful of operations that map generation used to get
+/// from libtcod: noise, hills, normalizing and rain erosion.
+pub struct Heightmap {
+    size: Point2D,
+    values: Vec<f32>,
+}
+
+impl Heightmap {
+    pub fn new(size: Point2D) -> Heightmap {
+        Heightmap {
+            size: size,
+            values: vec![0.0; size.0 * size.1],
+        }
+    }
+
+    pub fn size(&self) -> Point2D { self.size }
+
+    /// The height at the point, or 0 outside the map.
+    pub fn value(&self, x: usize, y: usize) -> f32 {
+        if x < self.size.0 && y < self.size.1 {
+            self.values[y * self.size.0 + x]
+        } else {
+            0.0
+        }
+    }
+
+    fn neighbours(&self, x: usize, y: usize) -> Vec<Point2D> {
+        NEIGHBOURS.iter()
+                  .map(|&(dx, dy)| (x as isize + dx, y as isize + dy))
+                  .filter(|&(nx, ny)| {
+                              nx >= 0 && ny >= 0 &&
+                                  (nx as usize) < self.size.0 &&
+                                  (ny as usize) < self.size.1
+                          })
+                  .map(|(nx, ny)| (nx as usize, ny as usize))
+                  .collect()
+    }
+
+    /// Adds fractal noise to every cell. The cell coordinates are
+    /// scaled to 0..1 and then by `mul` after shifting them by `add`.
+    pub fn add_fbm(&mut self,
+                   noise: &Noise,
+                   mul: (f32, f32),
+                   add: (f32, f32),
+                   octaves: u32,
+                   delta: f32,
+                   scale: f32) {
+        let (w, h) = self.size;
+        let (xcoef, ycoef) = (mul.0 / w as f32, mul.1 / h as f32);
+        for y in 0..h {
+            for x in 0..w {
+                let f = [(x as f32 + add.0) * xcoef,
+                         (y as f32 + add.1) * ycoef];
+                self.values[y * w + x] += delta +
+                    noise.get_fbm(&f, octaves) * scale;
+            }
+        }
+    }
+
+    /// Applies the operation to every cell within the radius, given
+    /// the cell's height and how far inside the circle it is.
+    fn hill<F>(&mut self, (hx, hy): (f32, f32), radius: f32, op: F)
+        where F: Fn(f32, f32) -> f32
+    {
+        let (w, h) = self.size;
+        let (minx, maxx) = ((hx - radius).max(0.0) as usize,
+                            ((hx + radius).min(w as f32)) as usize);
+        let (miny, maxy) = ((hy - radius).max(0.0) as usize,
+                            ((hy + radius).min(h as f32)) as usize);
+        let radius2 = radius * radius;
+        for y in miny..maxy {
+            for x in minx..maxx {
+                let (dx, dy) = (x as f32 - hx, y as f32 - hy);
+                let inside = radius2 - dx * dx - dy * dy;
+                if inside > 0.0 {
+                    let v = self.values[y * w + x];
+                    self.values[y * w + x] = op(v, inside);
+                }
+            }
+        }
+    }
+
+    /// Raises a rounded hill of the given height.
+    pub fn add_hill(&mut self,
+                    pos: (f32, f32),
+                    radius: f32,
+                    height: f32) {
+        let coef = height / (radius * radius);
+        self.hill(pos, radius, |v, inside| v + inside * coef);
+    }
+
+    /// Flattens the area into the shape of a hill of the given
+    /// height: cells are raised up to it, or with a negative height,
+    /// dug down to it.
+    pub fn dig_hill(&mut self,
+                    pos: (f32, f32),
+                    radius: f32,
+                    height: f32) {
+        let coef = height / (radius * radius);
+        self.hill(pos, radius, |v, inside| {
+            let z = inside * coef;
+            if (height > 0.0 && v < z) || (height <= 0.0 && v > z) {
+                z
+            } else {
+                v
+            }
+        });
+    }
+
+    /// Stretches the heights to fill the range from min to max.
+    pub fn normalize(&mut self, min: f32, max: f32) {
+        let (lo, hi) = self.values
+                           .iter()
+                           .fold((std::f32::MAX, std::f32::MIN),
+                                 |(lo, hi), v| {
+                                     (lo.min(*v), hi.max(*v))
+                                 });
+        for v in self.values.iter_mut() {
+            *v = if hi - lo == 0.0 {
+                min
+            } else {
+                min + (*v - lo) * (max - min) / (hi - lo)
+            };
+        }
+    }
+
+    /// Lets raindrops fall on random cells and run downhill, wearing
+    /// away each slope they cross and dropping what they carry where
+    /// they come to rest.
+    pub fn rain_erosion<R: Rng>(&mut self,
+                                drops: usize,
+                                erosion: f32,
+                                sedimentation: f32,
+                                rng: &mut R) {
+        let w = self.size.0;
+        for _ in 0..drops {
+            let (mut x, mut y) = (rng.gen_range(0, self.size.0),
+                                  rng.gen_range(0, self.size.1));
+            let mut sediment = 0.0;
+            loop {
+                let v = self.value(x, y);
+                // The steepest way down, if there is one.
+                let (slope, (nx, ny)) =
+                    self.neighbours(x, y)
+                        .into_iter()
+                        .map(|(nx, ny)| {
+                                 (v - self.value(nx, ny), (nx, ny))
+                             })
+                        .fold((0.0, (x, y)), |best, n| {
+                            if n.0 > best.0 { n } else { best }
+                        });
+                if slope > 0.0 {
+                    self.values[y * w + x] -= erosion * slope;
+                    sediment += slope;
+                    x = nx;
+                    y = ny;
+                } else {
+                    let deposit = sedimentation * sediment;
+                    self.values[y * w + x] += deposit;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// The steepness at the point, as an angle in radians.
+    pub fn slope(&self, x: usize, y: usize) -> f32 {
+        let v = self.value(x, y);
+        let (lo, hi) = self.neighbours(x, y)
+                           .into_iter()
+                           .map(|(nx, ny)| self.value(nx, ny) - v)
+                           .fold((0.0f32, 0.0f32), |(lo, hi), d| {
+                               (lo.min(d), hi.max(d))
+                           });
+        (lo + hi).atan2(1.0)
+    }
+}
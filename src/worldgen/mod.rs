@@ -1,26 +1,49 @@
 extern crate rand;
-extern crate tcod_sys;
 
 use std;
+use std::cmp;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::ops::{Index, Range};
 
-use tcod::noise::{Noise, NoiseType};
-use tcod::random;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::ser::SerializeStruct;
 
+pub mod heightmap;
+pub mod noise;
+pub mod stockpile;
 pub mod terrain;
-use self::rand::Rng;
+use self::heightmap::Heightmap;
+use self::noise::Noise;
+use self::rand::{IsaacRng, Rng};
 use self::rand::SeedableRng;
+use self::stockpile::Stockpile;
 use self::terrain::*;
 
-use life::{Living, MissionResult, Order};
+use draw::Describe;
 
+use jobs;
+use jobs::JobBoard;
+
+use life::{Creature, Living, MissionResult, Order};
+use life::animal::Species;
+
+use physics;
 use physics::PhysicsActor;
+use physics::fire::Fires;
+use physics::liquid;
+use physics::liquid::Flow;
+use physics::stone::Settling;
+
+use random::Streams;
 
-use time::{Calendar, Clock, Time};
+use time::{Calendar, CalendarConfig, Clock, MINUTES_PER_TICK, Season,
+           Time};
 
-use utils::{Point2D, Point3D, strict_adjacent};
+use utils::{Point2D, Point3D, rect_points, strict_adjacent};
+
+use weather;
+use weather::Front;
 
 /// Returns the Some() of the restricted version of a Tile if it can be restricted, if not, returns None.
 fn restricted_from_tile(tile: Tile) -> Option<RestrictedTile> {
@@ -35,7 +58,7 @@ fn restricted_from_tile(tile: Tile) -> Option<RestrictedTile> {
 
 /// A 1x1 cross section of the layered world, including a ref to the
 /// biome it is part of.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Unit {
     pub biome: Option<Biome>,
     pub tiles: RefCell<Vec<Tile>>,
@@ -57,7 +80,7 @@ pub type BiomeMap = HashMap<String, RefCell<Vec<(usize, usize)>>>;
 /// * the animation state
 /// * the map size and unit map
 pub struct World {
-    heightmap: *mut tcod_sys::TCOD_heightmap_t,
+    heightmap: Heightmap,
     stone_vein_noise: Noise,
     seed: u32,
     pub map_size: Point2D,
@@ -65,6 +88,8 @@ pub struct World {
     pub biome_map: BiomeMap,
     pub map: WorldMap,
     pub life: Vec<RefCell<Box<Living>>>,
+    pub regrowth: Vec<(Point3D, Tile, usize)>,
+    pub stockpiles: Vec<Stockpile>,
 }
 
 impl Index<usize> for World {
@@ -81,12 +106,83 @@ impl Index<Range<usize>> for World {
     }
 }
 
+/// The parts of a world that go into a save. The heightmap and the
+/// stone noise are only needed while generating the map, so they are
+/// left out and rebuilt from the seed on loading.
+#[derive(Deserialize)]
+struct SavedWorld {
+    seed: u32,
+    map_size: Point2D,
+    frames: Frames,
+    biome_map: BiomeMap,
+    map: WorldMap,
+    life: Vec<Creature>,
+    regrowth: Vec<(Point3D, Tile, usize)>,
+    stockpiles: Vec<Stockpile>,
+}
+
+impl Serialize for World {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let life = self.life
+                       .iter()
+                       .map(|actor| actor.borrow().snapshot())
+                       .collect::<Vec<_>>();
+        let mut state = serializer.serialize_struct("World", 8)?;
+        state.serialize_field("seed", &self.seed)?;
+        state.serialize_field("map_size", &self.map_size)?;
+        state.serialize_field("frames", &self.frames)?;
+        state.serialize_field("biome_map", &self.biome_map)?;
+        state.serialize_field("map", &self.map)?;
+        state.serialize_field("life", &life)?;
+        state.serialize_field("regrowth", &self.regrowth)?;
+        state.serialize_field("stockpiles", &self.stockpiles)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for World {
+    fn deserialize<D>(deserializer: D) -> Result<World, D::Error>
+        where D: Deserializer<'de>
+    {
+        let saved = SavedWorld::deserialize(deserializer)?;
+        let mut world = World::blank(saved.map_size, saved.seed);
+        world.frames = saved.frames;
+        world.biome_map = saved.biome_map;
+        world.map = saved.map;
+        world.life = saved.life
+                          .into_iter()
+                          .map(|c| RefCell::new(c.into_living()))
+                          .collect();
+        world.regrowth = saved.regrowth;
+        world.stockpiles = saved.stockpiles;
+        Ok(world)
+    }
+}
+
+/// The size of the worlds the game generates.
+pub const MAP_SIZE: Point2D = (110, 70);
+
 const THRESHOLD: f32 = 0.3;
 const SEA_LEVEL: Cell<f32> = Cell::new(13.0);
 const WATER_LEVEL: f32 = 7.0;
+/// Magma pools in pockets only this close to the bottom of the world.
+const MAGMA_DEPTH: isize = 4;
+/// How far the stone vein noise has to dip down there for the rock to
+/// be molten.
+const MAGMA_NOISE: f32 = -20.0;
+/// About as high as the heightmap goes: the coldest ground.
+const MOUNTAIN_LEVEL: f32 = 32.0;
 const VEG_THRESHOLD: f32 = 200.0;
 const RAMP_THRESHOLD: f32 = 0.015;
 const ANIMAL_COUNT: usize = 50;
+const PARTY_SIZE: usize = 3;
+const TREE_REGROWTH: usize = 6000;
+const PLANT_REGROWTH: usize = 1500;
+const REGROWTH_RETRY: usize = 300;
+/// The day, month and year new games start on.
+const START_DATE: (usize, usize, usize) = (12, 6, 100);
 
 impl World {
     /// Generates a new hightmap-based world map of the specified
@@ -100,11 +196,20 @@ impl World {
     /// * dig randomly sized hills
     pub fn new(size: Point2D, seed: u32) -> World {
         println!("Generating world from seed {}", seed);
-        let rng = random::Rng::new_with_seed(random::Algo::MT, seed);
-        SEA_LEVEL.set(rng.get_float(14.0, 17.0));
+        let mut world = Self::blank(size, seed);
+        world.map = Self::map_from(size, &world);
+        world.settle_purity();
+        world
+    }
+
+    /// Sets up the heightmap, noise and animation state for a world,
+    /// but leaves the map itself and its inhabitants empty.
+    fn blank(size: Point2D, seed: u32) -> World {
+        let mut rng = IsaacRng::from_seed(&[seed]);
+        SEA_LEVEL.set(rng.gen_range(14.0, 17.0));
 
         // Vegetation
-        let mut world: World = World {
+        World {
             map_size: size,
             heightmap: Self::generate_heightmap(size, seed),
             map: vec![],
@@ -113,13 +218,10 @@ impl World {
                 .cloned()
                 .map(|x| (x.to_string(), RefCell::new(vec![])))
                 .collect(),
-            stone_vein_noise: Noise::init_with_dimensions(3)
-                .lacunarity(0.43)
-                .hurst(-0.9)
-                .noise_type(NoiseType::Simplex)
-                .random(rng)
-                .init(),
+            stone_vein_noise: Noise::new(-0.9, 0.43, &mut rng),
             life: vec![],
+            regrowth: vec![],
+            stockpiles: vec![],
             seed: seed,
             frames: [("Water".to_string(),
                       vec![16, 32, 33, 34, 35, 36])]
@@ -127,26 +229,62 @@ impl World {
                     .cloned()
                     .map(|(x, y)| (x, y))
                     .collect(),
+        }
+    }
+
+    /// A world built by hand out of columns of tiles, given row by
+    /// row, all of it pasture with no one living in it.
+    #[cfg(test)]
+    pub fn from_columns(columns: Vec<Vec<Vec<Tile>>>) -> World {
+        let size = (columns[0].len(), columns.len());
+        let mut world = Self::blank(size, 0);
+        let pasture = Biome {
+            biome_type: BiomeType::Pasture,
+            temperature_night_f: 50.0,
+            temperature_day_f: 70.0,
+            percipitation_chance: 30.0,
         };
-        world.map = Self::map_from(size, &world);
+        world.map = columns.into_iter()
+                           .map(|row| {
+            row.into_iter()
+               .map(|tiles| {
+                        Unit {
+                            biome: Some(pasture),
+                            tiles: RefCell::new(tiles),
+                        }
+                    })
+               .collect()
+        })
+                           .collect();
         world
     }
 
+    /// The seed the world was generated from.
+    pub fn seed(&self) -> u32 { self.seed }
+
     pub fn kill(&mut self, i: usize) {
-        let l = self.life.remove(i).into_inner();
+        let mut l = self.life.remove(i).into_inner();
         let pos = l.current_pos();
-        self.map[pos.0][pos.1]
-            .tiles
-            .borrow_mut()
-            [pos.2] = Tile::Item(Item::Food(Food::Meat(l.species()
-                                                        .species)))
+        // Nothing is left of whatever dies in molten stone. Creatures
+        // can stand right on top of a column, so the carcass may have
+        // to go above it.
+        let molten = matches!(self.tile_at(pos),
+                              Some(Tile::Stone(_, State::Liquid)));
+        if !molten {
+            let meat = Food::Meat(l.species().species);
+            self.place(pos, Tile::Item(Item::Food(meat)));
+        }
+        // Whatever it carried ends up on top of the carcass.
+        if let Some(item) = l.drop_carried() {
+            self.put_down(pos, item);
+        }
     }
 
-    pub fn create_life_by_biome(pnt: Point3D,
-                                biome: Biome)
+    pub fn create_life_by_biome<R: Rng>(pnt: Point3D,
+                                        biome: Biome,
+                                        rng: &mut R)
         -> Option<Box<Living>> {
         use life::animal::*;
-        let mut trng = rand::thread_rng();
         let species = match biome.biome_type {
             BiomeType::Water => {
                 if biome.temperature_night_f < 50.0 {
@@ -161,7 +299,7 @@ impl World {
                 return None;
             }
             BiomeType::Forest => {
-                *trng.choose(
+                *rng.choose(
                     &[if biome.temperature_day_f > 70.0 {
                           Species::Carnivore(Carnivore::Dog)
                       } else if biome.temperature_day_f < 60.0 {
@@ -175,7 +313,7 @@ impl World {
                      .unwrap()
             }
             BiomeType::Jungle => {
-                *trng.choose(
+                *rng.choose(
                     &[
                         Species::Herbivore(Herbivore::Hippo),
                         Species::Carnivore(Carnivore::Alligator),
@@ -185,7 +323,7 @@ impl World {
                 ).unwrap()
             }
             BiomeType::Pasture => {
-                *trng.choose(
+                *rng.choose(
                     &[
                         Species::Herbivore(Herbivore::Sheep),
                         Species::Herbivore(Herbivore::Cow),
@@ -197,7 +335,7 @@ impl World {
                 ).unwrap()
             }
             BiomeType::Swamp => {
-                *trng.choose(
+                *rng.choose(
                     &[
                         Species::Carnivore(Carnivore::Alligator),
                         Species::Carnivore(Carnivore::Shark),
@@ -217,10 +355,13 @@ impl World {
     pub fn generate_life(&mut self) {
         let mut rng = rand::IsaacRng::from_seed(&[self.seed]);
         for _ in 0..ANIMAL_COUNT {
-            let bks = self.biome_map
-                          .iter()
-                          .map(|(k, _)| k.clone())
-                          .collect::<Vec<_>>();
+            // Sorted, since the biome map's own order changes from
+            // run to run.
+            let mut bks = self.biome_map
+                              .iter()
+                              .map(|(k, _)| k.clone())
+                              .collect::<Vec<_>>();
+            bks.sort();
             let chosen_biome = rng.choose(&bks).unwrap();
             let bps = &self.biome_map[chosen_biome];
             if let Some(point) = rng.choose(&bps.borrow()) {
@@ -233,7 +374,8 @@ impl World {
                     let animal =
                         World::create_life_by_biome(p3d,
                                                     unit.biome
-                                                        .unwrap());
+                                                        .unwrap(),
+                                                    &mut rng);
                     if let Some(animal) = animal {
                         self.life.push(RefCell::new(animal));
                     }
@@ -242,10 +384,53 @@ impl World {
         }
     }
 
-    unsafe fn get_slope(&self, x: usize, y: usize) -> f32 {
-        tcod_sys::TCOD_heightmap_get_slope(self.heightmap,
-                                           x as i32,
-                                           y as i32)
+    /// Tests if a point is dry, open ground that a settler could
+    /// stand on.
+    fn dry_land(&self, pnt: Point2D) -> bool {
+        let z = self.location_z(pnt);
+        let wet = self.unit_at(pnt)
+                      .and_then(|u| u.biome)
+                      .map_or(true, |b| b.biome_type == BiomeType::Water);
+        !wet &&
+            self.tile_at((pnt.0, pnt.1, z))
+                .map_or(false, |t| !t.solid() && !World::is_water(t))
+    }
+
+    /// Places the starting party of dwarves on the dry land closest to
+    /// the center of the map.
+    pub fn generate_party(&mut self) {
+        use life::dwarf::Dwarf;
+        let (cx, cy) = (self.map_size.0 as isize / 2,
+                        self.map_size.1 as isize / 2);
+        let start = (0..self.map_size.1)
+            .flat_map(|y| (0..self.map_size.0).map(move |x| (x, y)))
+            .filter(|pnt| self.dry_land(*pnt))
+            .min_by_key(|&(x, y)| {
+                let (dx, dy) = (x as isize - cx, y as isize - cy);
+                (dx * dx + dy * dy, y, x)
+            });
+        if let Some(start) = start {
+            let mut spots = vec![start];
+            spots.extend(strict_adjacent(start)
+                             .into_iter()
+                             .filter(|pnt| {
+                                         *pnt != start &&
+                                             self.dry_land(*pnt)
+                                     }));
+            let pnts = (0..PARTY_SIZE)
+                .map(|i| {
+                         let (x, y) = spots[i % spots.len()];
+                         (x, y, self.location_z((x, y)))
+                     })
+                .collect();
+            for dwarf in Dwarf::party(pnts) {
+                self.life.push(RefCell::new(dwarf));
+            }
+        }
+    }
+
+    fn get_slope(&self, x: usize, y: usize) -> f32 {
+        self.heightmap.slope(x, y)
     }
 
     /// Step 1 of map generation:
@@ -258,7 +443,7 @@ impl World {
             .map(|y| {
                 (0..sw)
                     .map(|x| {
-                        let height = unsafe { ws.get_height(x, y) } as usize;
+                        let height = ws.get_height(x, y) as usize;
                         Unit {
                             biome: None,
                             tiles: RefCell::new(
@@ -274,7 +459,8 @@ impl World {
     }
 
     /// Step 2 of map generation:
-    /// Replace low rock with water of a similar depth.
+    /// Replace low rock with water of a similar depth, filled up to
+    /// sea level.
     /// The sea level is raised when inland to allow for rivers and pools.
     fn water_from_low(world: WorldMap) -> WorldMap {
         world.iter()
@@ -298,10 +484,14 @@ impl World {
 
                 let ut = unit.tiles.borrow();
                 let unit_height = ut.len();
+                // The sea is filled up to one level, so that it
+                // starts out at rest rather than running downhill.
+                let sea_height = SEA_LEVEL.get() as usize - 1;
+                let water_height = cmp::max(unit_height, sea_height);
                 let water_unit = Unit {
                     biome: Some(WATER_BIOME),
                     tiles: RefCell::new(
-                        (0..unit_height)
+                        (0..water_height)
                             .map(|depth| if depth <=
                                 unit_height.checked_sub(
                                     WATER_LEVEL as usize,
@@ -310,10 +500,9 @@ impl World {
                             {
                                 ut[depth].clone()
                             } else {
-                                Tile::Water(World::purity(),
+                                Tile::Water(LiquidPurity::Clean,
                                             State::Liquid,
-                                            unit_height as i32 -
-                                                depth as i32)
+                                            FULL_DEPTH)
                             })
                             .collect(),
                     ),
@@ -337,13 +526,8 @@ impl World {
     fn biomes_from_height_and_noise(world_map: WorldMap,
                                     world: &World)
         -> WorldMap {
-        let bnoise = Noise::init_with_dimensions(2)
-            .lacunarity(0.43)
-            .hurst(-0.9)
-            .noise_type(NoiseType::Simplex)
-            .random(random::Rng::new_with_seed(random::Algo::MT,
-                                               world.seed))
-            .init();
+        let mut rng = IsaacRng::from_seed(&[world.seed]);
+        let bnoise = Noise::new(-0.9, 0.43, &mut rng);
         world_map.iter()
                  .enumerate()
                  .map(|(y, row)| {
@@ -360,7 +544,7 @@ impl World {
                         })
                         .filter_map(|x| x)
                         .fold((0, 0), |(s, n), x| (s + x, n + 1));
-                let noise = bnoise.get_fbm([x as f32, y as f32], 6) *
+                let noise = bnoise.get_fbm(&[x as f32, y as f32], 6) *
                     100.0;
                 let biome = World::biome_from_noise(noise as i32,
                                                     (sh as i32 / n) as
@@ -384,13 +568,8 @@ impl World {
     fn vegitation_from_biomes(world: WorldMap,
                               seed: u32)
         -> WorldMap {
-        let vnoise = Noise::init_with_dimensions(2)
-            .lacunarity(0.3)
-            .hurst(-0.9)
-            .noise_type(NoiseType::Simplex)
-            .random(random::Rng::new_with_seed(random::Algo::MT,
-                                               seed))
-            .init();
+        let mut rng = IsaacRng::from_seed(&[seed]);
+        let vnoise = Noise::new(-0.9, 0.3, &mut rng);
         world.iter()
              .enumerate()
              .map(|(y, row)| {
@@ -401,7 +580,8 @@ impl World {
                     let mut tiles = unit.tiles.clone().into_inner();
                     tiles.push(World::get_vegetation(&vnoise,
                                                      (x, y),
-                                                     biome));
+                                                     biome,
+                                                     &mut rng));
                     Unit {
                         biome: unit.biome,
                         tiles: RefCell::new(tiles),
@@ -479,7 +659,7 @@ impl World {
         pipe!(
             vec![]
                 => {|i| rock_from_terrain(ws, size, i)}
-            => water_from_low
+            => { |x| water_from_low(x) }
             => { |x| biomes_from_height_and_noise(x, ws) }
             => { |x| vegitation_from_biomes(x, ws.seed) }
             => { |x| add_soil(x, ws.seed) }
@@ -502,126 +682,87 @@ impl World {
     }
 
     /// A general method for dealing with generating random hills of a limited size, position, and height.
-    fn random_hill_operation<F>(
-        heightmap: *mut tcod_sys::TCOD_heightmap_t,
-        (hmw, hmh): Point2D,
-        num_hills: i32,
-        base_radius: f32,
-        radius: f32,
-        height: f32,
-        rndn: tcod_sys::TCOD_random_t,
-        operation: &F,
-    ) where
-        F: Fn(*mut tcod_sys::TCOD_heightmap_t, f32, f32, f32, f32),
+    fn random_hill_operation<F>(heightmap: &mut Heightmap,
+                                num_hills: usize,
+                                base_radius: f32,
+                                radius: f32,
+                                height: f32,
+                                rng: &mut IsaacRng,
+                                operation: &F)
+        where F: Fn(&mut Heightmap, (f32, f32), f32, f32)
     {
-        unsafe {
-            for _ in 0..num_hills {
-                let radius =
-                    tcod_sys::TCOD_random_get_float(rndn,
-                                                    base_radius *
-                                                        (1.0 -
-                                                             radius),
-                                                    base_radius *
-                                                        (1.0 +
-                                                             radius));
-                let xh = tcod_sys::TCOD_random_get_int(rndn,
-                                                       0,
-                                                       hmw as i32 -
-                                                           1);
-                let yh = tcod_sys::TCOD_random_get_int(rndn,
-                                                       0,
-                                                       hmh as i32 -
-                                                           1);
-                operation(heightmap,
-                          xh as f32,
-                          yh as f32,
-                          radius,
-                          height);
-            }
+        let (hmw, hmh) = heightmap.size();
+        for _ in 0..num_hills {
+            let radius = rng.gen_range(base_radius * (1.0 - radius),
+                                       base_radius * (1.0 + radius));
+            let xh = rng.gen_range(0, hmw);
+            let yh = rng.gen_range(0, hmh);
+            operation(heightmap,
+                      (xh as f32, yh as f32),
+                      radius,
+                      height);
         }
     }
 
     /// Extrudes random hills.
-    fn add_random_hills(hm: *mut tcod_sys::TCOD_heightmap_t,
-                        sz: Point2D,
-                        nh: i32,
+    fn add_random_hills(hm: &mut Heightmap,
+                        nh: usize,
                         br: f32,
                         r: f32,
                         h: f32,
-                        n: tcod_sys::TCOD_random_t) {
+                        n: &mut IsaacRng) {
         Self::random_hill_operation(hm,
-                                    sz,
                                     nh,
                                     br,
                                     r,
                                     h,
                                     n,
-                                    &|a, b, c, d, e| unsafe { tcod_sys::TCOD_heightmap_add_hill(a, b, c, d, e) });
+                                    &Heightmap::add_hill);
     }
 
     /// Digs random hills.
-    fn dig_random_hills(hm: *mut tcod_sys::TCOD_heightmap_t,
-                        sz: Point2D,
-                        nh: i32,
+    fn dig_random_hills(hm: &mut Heightmap,
+                        nh: usize,
                         br: f32,
                         r: f32,
                         h: f32,
-                        n: tcod_sys::TCOD_random_t) {
+                        n: &mut IsaacRng) {
         Self::random_hill_operation(hm,
-                                    sz,
                                     nh,
                                     br,
                                     r,
                                     h,
                                     n,
-                                    &|a, b, c, d, e| unsafe { tcod_sys::TCOD_heightmap_dig_hill(a, b, c, d, e) });
+                                    &Heightmap::dig_hill);
     }
 
-    /// Generates a new heightmap: this is all unsafe code, and uses the low-level FFI to libtcod 5.1.
-    fn generate_heightmap((sx, sy): Point2D,
-                          seed: u32)
-        -> *mut tcod_sys::TCOD_heightmap_t {
-        let heightmap = unsafe {
-            tcod_sys::TCOD_heightmap_new(sx as i32, sy as i32)
-        };
-        unsafe {
-            let rndn = tcod_sys::TCOD_random_new_from_seed(tcod_sys::TCOD_RNG_MT, seed);
-            let noise = tcod_sys::TCOD_noise_new(2, 0.7, 0.1, rndn);
-            tcod_sys::TCOD_noise_set_type(noise, tcod_sys::TCOD_NOISE_SIMPLEX);
-            tcod_sys::TCOD_heightmap_add_fbm(heightmap,
-                                             noise,
-                                             2.20 * (sx as f32) /
-                                                 400.0,
-                                             2.20 * (sx as f32) /
-                                                 400.0,
-                                             0.0,
-                                             0.0,
-                                             10.0,
-                                             1.0,
-                                             4.0);
-            Self::add_random_hills(heightmap,
-                                   (sx, sy),
-                                   600,
-                                   16.0 * sx as f32 / 200.0,
-                                   0.7,
-                                   0.3,
-                                   rndn);
-            Self::dig_random_hills(heightmap,
-                                   (sx, sy),
-                                   300,
-                                   16.0 * sx as f32 / 200.0,
-                                   0.6,
-                                   0.3,
-                                   rndn);
-            tcod_sys::TCOD_heightmap_normalize(heightmap, 0.0, 100.0);
-            tcod_sys::TCOD_heightmap_rain_erosion(heightmap,
-                                                  (sx * sy + 100) as
-                                                      i32,
-                                                  0.06,
-                                                  0.02,
-                                                  rndn);
-            tcod_sys::TCOD_heightmap_normalize(heightmap, 0.0, 100.0);
-        };
+    /// Generates a new heightmap from noise, hills and rain erosion.
+    fn generate_heightmap((sx, sy): Point2D, seed: u32) -> Heightmap {
+        let mut rng = IsaacRng::from_seed(&[seed]);
+        let noise = Noise::new(0.7, 0.1, &mut rng);
+        let mut heightmap = Heightmap::new((sx, sy));
+        heightmap.add_fbm(&noise,
+                          (2.20 * (sx as f32) / 400.0,
+                           2.20 * (sx as f32) / 400.0),
+                          (0.0, 0.0),
+                          10,
+                          1.0,
+                          4.0);
+        Self::add_random_hills(&mut heightmap,
+                               600,
+                               16.0 * sx as f32 / 200.0,
+                               0.7,
+                               0.3,
+                               &mut rng);
+        Self::dig_random_hills(&mut heightmap,
+                               300,
+                               16.0 * sx as f32 / 200.0,
+                               0.6,
+                               0.3,
+                               &mut rng);
+        heightmap.normalize(0.0, 100.0);
+        heightmap.rain_erosion(sx * sy + 100, 0.06, 0.02, &mut rng);
+        heightmap.normalize(0.0, 100.0);
         heightmap
     }
 
@@ -634,45 +775,289 @@ impl World {
         }
     }
 
+    /// Get a reference to the unit at the specified position, without
+    /// copying it.
+    pub fn unit_at(&self, pos: Point2D) -> Option<&Unit> {
+        self.map.get(pos.1).and_then(|row| row.get(pos.0))
+    }
+
+    /// Get the tile at the specified 3D position, if there is one.
+    pub fn tile_at(&self, pos: Point3D) -> Option<Tile> {
+        self.unit_at((pos.0, pos.1))
+            .and_then(|u| u.tiles.borrow().get(pos.2).cloned())
+    }
+
+    /// Digs out the solid stone at the given point, leaving rubble of
+    /// the same stone behind. The rest of the column is left as it is,
+    /// for the physics to settle. Returns the kind of stone dug out, or
+    /// None if there was no stone to dig.
+    pub fn dig(&self, (x, y, z): Point3D) -> Option<StoneTypes> {
+        let unit = get!(self.unit_at((x, y)));
+        let mut tiles = unit.tiles.borrow_mut();
+        let kind = match tiles.get(z) {
+            Some(&Tile::Stone(kind, State::Solid)) => kind,
+            _ => return None,
+        };
+        tiles[z] = Tile::Item(Item::Material(Material::Stone(kind)));
+        Some(kind)
+    }
+
+    /// Puts a tile in place at the given point, which can be anywhere
+    /// in a column or right on top of it. Returns false if the point
+    /// is out of reach.
+    pub fn place(&self, (x, y, z): Point3D, tile: Tile) -> bool {
+        match self.unit_at((x, y)) {
+            Some(unit) => {
+                let mut tiles = unit.tiles.borrow_mut();
+                if z < tiles.len() {
+                    tiles[z] = tile;
+                    true
+                } else if z == tiles.len() {
+                    tiles.push(tile);
+                    true
+                } else {
+                    false
+                }
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces the tile at the given point. Plants that are removed
+    /// this way are remembered, so that they can grow back later.
+    pub fn replace(&mut self, pnt: Point3D, tile: Tile) {
+        let old = self.tile_at(pnt);
+        if let Some(Tile::Vegetation(vt, ..)) = old {
+            let time = if vt.is_tree() {
+                TREE_REGROWTH
+            } else {
+                PLANT_REGROWTH
+            };
+            self.regrowth.push((pnt, old.unwrap(), time));
+        }
+        self.place(pnt, tile);
+        self.take_stock(pnt);
+    }
+
+    /// Puts an item down at the given point, or on the first open
+    /// tile above it if something is already there. Returns where the
+    /// item was put, or None if the point is off the map.
+    pub fn put_down(&self, (x, y, z): Point3D, item: Item)
+        -> Option<Point3D> {
+        let unit = get!(self.unit_at((x, y)));
+        let mut tiles = unit.tiles.borrow_mut();
+        let mut z = z;
+        while z < tiles.len() && tiles[z] != Tile::Empty {
+            z += 1;
+        }
+        while tiles.len() <= z {
+            tiles.push(Tile::Empty);
+        }
+        tiles[z] = Tile::Item(item);
+        Some((x, y, z))
+    }
+
+    /// The loose items lying in a column, which are the items above
+    /// its topmost solid tile.
+    pub fn items_at(&self, (x, y): Point2D) -> Vec<(Point3D, Item)> {
+        self.unit_at((x, y)).map_or(vec![], |unit| {
+            let tiles = unit.tiles.borrow();
+            let top = tiles.iter()
+                           .rposition(|t| t.solid())
+                           .map_or(0, |z| z + 1);
+            tiles.iter()
+                 .enumerate()
+                 .skip(top)
+                 .filter_map(|(z, t)| match t {
+                                 &Tile::Item(i) => Some(((x, y, z), i)),
+                                 _ => None,
+                             })
+                 .collect()
+        })
+    }
+
+    /// All loose items on the map that are not in a stockpile yet.
+    pub fn loose_items(&self) -> Vec<(Point3D, Item)> {
+        (0..self.map_size.1)
+            .flat_map(|y| (0..self.map_size.0).map(move |x| (x, y)))
+            .filter(|pnt| self.stockpile_at(*pnt).is_none())
+            .flat_map(|pnt| self.items_at(pnt))
+            .collect()
+    }
+
+    pub fn stockpile_at(&self, pnt: Point2D) -> Option<&Stockpile> {
+        self.stockpiles.iter().find(|s| s.contains(pnt))
+    }
+
+    /// Adds a stockpile zone, taking stock of what is already lying
+    /// in it. A stockpile over exactly the same area replaces the old
+    /// one.
+    pub fn add_stockpile(&mut self, mut stockpile: Stockpile) {
+        stockpile.inventory = rect_points(stockpile.area)
+            .into_iter()
+            .flat_map(|pnt| self.items_at(pnt))
+            .collect();
+        let area = stockpile.area;
+        self.stockpiles.retain(|s| s.area != area);
+        self.stockpiles.push(stockpile);
+    }
+
+    /// Brings the inventory of the stockpile covering a point, if any,
+    /// up to date with the items lying in that column.
+    pub fn take_stock(&mut self, (x, y, _): Point3D) {
+        let i = match self.stockpiles
+                          .iter()
+                          .position(|s| s.contains((x, y))) {
+            Some(i) => i,
+            None => return,
+        };
+        let items = self.items_at((x, y));
+        let inventory = &mut self.stockpiles[i].inventory;
+        inventory.retain(|&((ix, iy, _), _)| (ix, iy) != (x, y));
+        inventory.extend(items);
+    }
+
+    /// Finds the items in all stockpiles that pass the test.
+    pub fn stocked<F>(&self, test: F) -> Vec<(Point3D, Item)>
+        where F: Fn(&Item) -> bool
+    {
+        self.stockpiles
+            .iter()
+            .flat_map(|s| s.inventory.iter().cloned())
+            .filter(|&(_, ref item)| test(item))
+            .collect()
+    }
+
+    /// Lets harvested plants grow back once their time has come, as
+    /// long as the biome still suits them. If an item is in the way,
+    /// or the ground it grew on is out of reach, the plant tries
+    /// again a little later.
+    pub fn regrow(&mut self) {
+        let regrowth = std::mem::replace(&mut self.regrowth, vec![]);
+        for (pnt, plant, time) in regrowth {
+            if time > 0 {
+                self.regrowth.push((pnt, plant, time - 1));
+                continue;
+            }
+            let survives = match plant {
+                Tile::Vegetation(vt, ..) => {
+                    self.unit_at((pnt.0, pnt.1))
+                        .and_then(|u| u.biome)
+                        .map_or(false, |b| b.survives(vt))
+                }
+                _ => false,
+            };
+            if survives {
+                let retry = match self.tile_at(pnt) {
+                    Some(Tile::Empty) | None => {
+                        !self.place(pnt, plant)
+                    }
+                    Some(Tile::Item(..)) => true,
+                    _ => false,
+                };
+                if retry {
+                    self.regrowth.push((pnt, plant, REGROWTH_RETRY));
+                }
+            }
+        }
+    }
+
     /// Test if the given point is on the World plane.
     pub fn located_inside(&self, pos: Point2D) -> bool {
         return pos.0 < self.map_size.0 && pos.1 < self.map_size.1;
     }
 
-    /// For memory cleanup.
-    pub unsafe fn delete_heightmap(&self) {
-        tcod_sys::TCOD_heightmap_delete(self.heightmap);
+    /// Gets the height of the map at the current point.
+    fn get_height(&self, x: usize, y: usize) -> f32 {
+        self.heightmap.value(x, y) * THRESHOLD
     }
 
-    /// Gets the height of the map at the current point.
-    unsafe fn get_height(&self, x: usize, y: usize) -> f32 {
-        tcod_sys::TCOD_heightmap_get_value(self.heightmap,
-                                           x as i32,
-                                           y as i32) *
-            THRESHOLD
+    /// What the water at a point is made by what it touches.
+    /// Anything lying dead by it poisons it, running water stirs up
+    /// the soil it runs over, plants cloud still water and soil
+    /// dirties it, and bare rock keeps it from being any purer than
+    /// clear. What it touches only ever makes it dirtier than it
+    /// `was`: it clears by itself, a step at a time, as sand and mud
+    /// settle out once it stops `flowing`.
+    pub fn purity(&self,
+                  (x, y, z): Point3D,
+                  was: LiquidPurity,
+                  flowing: bool)
+        -> LiquidPurity {
+        let mut near = strict_adjacent((x, y))
+            .into_iter()
+            .filter(|&pos| pos != (x, y))
+            .map(|(ax, ay)| (ax, ay, z))
+            .collect::<Vec<_>>();
+        if z > 0 {
+            near.push((x, y, z - 1));
+        }
+        near.push((x, y, z + 1));
+        let tiles = near.into_iter()
+                        .filter_map(|p| self.tile_at(p))
+                        .collect::<Vec<_>>();
+        let touches = |test: &Fn(&Tile) -> bool| {
+            tiles.iter().any(|t| test(t))
+        };
+        let dead = |t: &Tile| {
+            matches!(*t, Tile::Item(Item::Food(Food::Meat(_))))
+        };
+        let soil = |t: &Tile| match t {
+            &Tile::Stone(StoneTypes::Soil(_), _) |
+            &Tile::Moveable(RestrictedTile::Stone(StoneTypes::Soil(_),
+                                                  _)) => true,
+            _ => false,
+        };
+        let plant = |t: &Tile| matches!(*t, Tile::Vegetation(..));
+        let rock = |t: &Tile| {
+            matches!(*t, Tile::Stone(_, State::Solid))
+        };
+        let around = if touches(&dead) {
+            LiquidPurity::Toxic
+        } else if flowing && touches(&|t| t.granular()) {
+            LiquidPurity::Sandy
+        } else if flowing && touches(&soil) {
+            LiquidPurity::Muddy
+        } else if touches(&plant) {
+            LiquidPurity::Murky
+        } else if touches(&soil) {
+            LiquidPurity::Dirty
+        } else if touches(&rock) {
+            LiquidPurity::Clear
+        } else {
+            LiquidPurity::Pure
+        };
+        let settled = if flowing {
+            was
+        } else {
+            match was {
+                LiquidPurity::Sandy => LiquidPurity::Clean,
+                LiquidPurity::Muddy => LiquidPurity::Dirty,
+                purity => purity,
+            }
+        };
+        cmp::max(settled, around)
     }
 
-    /// Gets the liquid purity.
-    ///
-    /// TODO: Make this based on proximity to dirt and plants, instead
-    /// of being random.
-    pub fn purity() -> LiquidPurity {
-        *rand::thread_rng()
-            .choose(&[LiquidPurity::Clean,
-                      LiquidPurity::Clear,
-                      LiquidPurity::Clear,
-                      LiquidPurity::Dirty,
-                      LiquidPurity::Dirty,
-                      LiquidPurity::Dirty,
-                      LiquidPurity::Dirty,
-                      LiquidPurity::Muddy,
-                      LiquidPurity::Muddy,
-                      LiquidPurity::Muddy,
-                      LiquidPurity::Murky,
-                      LiquidPurity::Pure,
-                      LiquidPurity::Sandy,
-                      LiquidPurity::Toxic])
-            .unwrap()
+    /// Works out the purity of all the water on a new map from what
+    /// it lies against.
+    fn settle_purity(&self) {
+        for (y, row) in self.map.iter().enumerate() {
+            for (x, unit) in row.iter().enumerate() {
+                let len = unit.tiles.borrow().len();
+                for z in 0..len {
+                    let (was, depth) = match self.tile_at((x, y, z)) {
+                        Some(Tile::Water(p, State::Liquid, d)) => {
+                            (p, d)
+                        }
+                        _ => continue,
+                    };
+                    let purity = self.purity((x, y, z), was, false);
+                    unit.tiles.borrow_mut()[z] =
+                        Tile::Water(purity, State::Liquid, depth);
+                }
+            }
+        }
     }
 
     pub fn len(&self) -> usize {
@@ -702,7 +1087,12 @@ impl World {
     pub fn biome_from_noise(point_val: i32,
                             avg_height: f32)
         -> Biome {
-        let temp_day = 180f32 - (avg_height * 210f32);
+        // 0 at the sea up to 1 at the peaks, which lose about 70F.
+        let altitude = ((avg_height - SEA_LEVEL.get()) /
+                            (MOUNTAIN_LEVEL - SEA_LEVEL.get()))
+            .max(0.0)
+            .min(1.0);
+        let temp_day = 95.0 - altitude * 70.0;
         Biome {
             biome_type: if avg_height < SEA_LEVEL.get() {
                 BiomeType::Beach
@@ -714,8 +1104,10 @@ impl World {
                 World::biome_type_from_noise(point_val)
             },
             temperature_day_f: temp_day,
-            temperature_night_f: 80f32 - (avg_height * 100f32),
-            percipitation_chance: point_val as f32,
+            temperature_night_f: 65.0 - altitude * 70.0,
+            percipitation_chance: ((point_val as f32 + 100.0) / 2.0)
+                .max(0.0)
+                .min(100.0),
         }
     }
 
@@ -786,13 +1178,17 @@ impl World {
         }
     }
 
-    /// Chooses a type of rock based on the World's stone_noise FBM and height.
+    /// Chooses a type of rock based on the World's stone_noise FBM
+    /// and height. Deep down, where the noise dips low enough, the
+    /// rock is molten basalt instead, in pockets of magma.
     pub fn rock_type(&self, (x, y): Point2D, height: isize) -> Tile {
         let rn = self.stone_vein_noise
-                     .get_fbm(&mut [x as f32,
-                                    y as f32,
-                                    height as f32],
+                     .get_fbm(&[x as f32, y as f32, height as f32],
                               2) * 100.0;
+        if height < MAGMA_DEPTH && rn < MAGMA_NOISE {
+            let basalt = StoneTypes::Igneous(IgneousRocks::Basalt);
+            return Tile::Stone(basalt, State::Liquid);
+        }
         let sedimentary = &[SedimentaryRocks::Conglomerate,
                             SedimentaryRocks::Limestone];
         let igneous = &[IgneousRocks::Obsidian, IgneousRocks::Basalt];
@@ -800,7 +1196,7 @@ impl World {
                             MetamorphicRocks::Gneiss];
         Tile::Stone(
             // Stone type
-            if height < 2 {
+            if height < MAGMA_DEPTH {
                 let v = World::rock_choice(igneous, rn);
                 StoneTypes::Igneous(v.clone())
             } else if height as f32 <= SEA_LEVEL.get() - 1.0 {
@@ -813,24 +1209,15 @@ impl World {
                 let v = World::rock_choice(metamorphic, rn);
                 StoneTypes::Metamorphic(v.clone())
             },
-
-            // State
-            if height == 0 {
-                if rn < 0.0 {
-                    State::Liquid
-                } else {
-                    State::Solid
-                }
-            } else {
-                State::Solid
-            },
+            State::Solid,
         )
     }
 
     /// Gets the correct vegitation based on the heightmap's height and random selection.
-    pub fn get_vegetation(noise: &Noise,
-                          (x, y): Point2D,
-                          biome: Biome)
+    pub fn get_vegetation<R: Rng>(noise: &Noise,
+                                  (x, y): Point2D,
+                                  biome: Biome,
+                                  rng: &mut R)
         -> Tile {
         let vn = noise.get_fbm(&mut [x as f32, y as f32], 1) * 100.0;
         let veg_levels = vec![[VegType::Bluegrass,
@@ -851,24 +1238,23 @@ impl World {
                               [VegType::Redwood,
                                VegType::Pine,
                                VegType::Banyon]];
-        let mut trng = rand::thread_rng();
         let (vopts, height) = if vn < 0.0 {
             (&veg_levels[0], 1)
         } else if vn < 2.0 {
-            (&veg_levels[1], trng.gen_range(1, 3))
+            (&veg_levels[1], rng.gen_range(1, 3))
         } else if vn < 15.0 {
-            (&veg_levels[2], trng.gen_range(4, 6))
+            (&veg_levels[2], rng.gen_range(4, 6))
         } else if vn < 20.0 {
-            (&veg_levels[3], trng.gen_range(6, 9))
+            (&veg_levels[3], rng.gen_range(6, 9))
         } else if vn < 40.0 {
-            (&veg_levels[4], trng.gen_range(7, 10))
+            (&veg_levels[4], rng.gen_range(7, 10))
         } else if vn < 100.0 {
-            (&veg_levels[5], trng.gen_range(10, 20))
+            (&veg_levels[5], rng.gen_range(10, 20))
         } else {
             (&veg_levels[5], vn as i32)
         };
 
-        if let Some(v) = trng.choose(
+        if let Some(v) = rng.choose(
             &vopts.iter()
                   .filter(|v| biome.survives(**v))
                   .collect::<Vec<_>>()
@@ -934,7 +1320,8 @@ impl World {
 /// * calendar date
 /// * time of day (fuzzy)
 /// * absolute time (clock)
-/// * days since universe start
+/// * days since the start of year 0
+#[derive(Serialize, Deserialize)]
 pub struct TimeHandler {
     pub calendar: Calendar,
     pub time_of_day: Time,
@@ -942,73 +1329,201 @@ pub struct TimeHandler {
     pub days: usize,
 }
 
+/// Something that happened during an update that the player would
+/// want to know about.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WorldEvent {
+    Death(Species),
+    OrderDone(Order),
+    /// Workers kept failing at a job, so it was dropped.
+    GaveUp(Order),
+    NewSeason(Season),
+    /// Stone came down at a point, so many blocks of it.
+    CaveIn(Point3D, usize),
+}
+
+impl Describe for WorldEvent {
+    fn describe(&self) -> String {
+        match self {
+            &WorldEvent::Death(species) => {
+                format!("A {:?} has died", species)
+            }
+            &WorldEvent::OrderDone(order) => {
+                format!("Finished {:?}", order)
+            }
+            &WorldEvent::GaveUp(order) => {
+                format!("Gave up on {:?}", order)
+            }
+            &WorldEvent::NewSeason(season) => {
+                format!("{:?} has come", season)
+            }
+            &WorldEvent::CaveIn((x, y, _), 1) => {
+                format!("A block fell at {}, {}", x, y)
+            }
+            &WorldEvent::CaveIn((x, y, _), blocks) => {
+                format!("A cave-in brought down {} blocks at {}, {}",
+                        blocks,
+                        x,
+                        y)
+            }
+        }
+    }
+}
+
 /// Handles overall world state:
 ///
 /// * the 3D screen location and cursor position
 /// * the organisms
+/// * player orders and the jobs they are split into
 /// * meta info about the map and the map itself
-/// * and time, including dates and clock time.
+/// * time, including dates and clock time
+/// * and the random number streams the simulation draws from.
 ///
 /// WorldState also handles generating a new map, which, for
 /// performance reasons, is not requred on the creation of the struct,
 /// instead relying on after-the-fact linking.
+#[derive(Serialize, Deserialize)]
 pub struct WorldState {
     pub screen: (i32, i32),
     pub cursor: (i32, i32),
     pub level: i32,
     pub commands: Vec<Order>,
+    pub jobs: JobBoard,
     pub map: Option<World>,
     pub highest_level: usize,
     pub time: TimeHandler,
+    pub rng: Streams,
+    /// How many times the world has been updated since it was made.
+    #[serde(default)]
+    pub tick: usize,
+    #[serde(default)]
+    pub weather: Front,
+    /// The liquids that are still moving. Saved along with the fires
+    /// and settling, so that a loaded game goes on exactly as the
+    /// saved one would have; saves from before they were kept scan
+    /// the map for liquid and fire again on their first tick.
+    #[serde(default)]
+    pub flow: Flow,
+    /// The fires burning on the map.
+    #[serde(default)]
+    pub fires: Fires,
+    /// Where stone may have lost its support.
+    #[serde(default)]
+    pub settling: Settling,
 }
 
 impl WorldState {
-    fn update_time(&mut self, time: usize, dt: usize) {
-        self.time.clock.update_deltatime(dt);
+    fn update_time(&mut self) -> Option<WorldEvent> {
+        let days = self.time.clock.update_deltatime(MINUTES_PER_TICK);
         self.time.time_of_day = Time::from_clock_time(&self.time
                                                            .clock);
-        if self.time.time_of_day == Time::Midnight {
-            self.time.clock.time = (0, 0);
-            self.time.days += 1;
-            self.time
-                .calendar
-                .update_to_day(self.time.days, &self.time.clock);
+        if days > 0 {
+            self.time.days += days;
+            let season = self.time.calendar.season;
+            self.time.calendar.update_to_day(self.time.days);
+            if self.time.calendar.season != season {
+                return Some(WorldEvent::NewSeason(self.time
+                                                      .calendar
+                                                      .season));
+            }
         }
+        None
     }
 
-    fn update_life(&mut self, time: usize) {
+    fn update_life(&mut self) -> Vec<WorldEvent> {
+        let time = self.tick;
+        let mut events = vec![];
         if let Some(ref mut world) = self.map {
+            // Actors are only removed once everyone has had their
+            // turn, so that the indices stay valid.
+            let mut dead = vec![];
+            // Actors take turns: half of them act every other tick, the
+            // rest every third.
             for i in 0..world.life.len() {
                 let modifier = if i % 2 == 0 { 2 } else { 3 };
-                if time % modifier == 0 {
+                if time % modifier == 0 && !dead.contains(&i) {
                     let res = {
                         let mut actor = world.life[i].borrow_mut();
-                        actor.execute_mission(world)
+                        actor.execute_mission(world,
+                                              &mut self.rng.life)
                     };
                     match res {
-                        MissionResult::Die => world.kill(i),
-                        MissionResult::Kill(i) => world.kill(i),
+                        MissionResult::Die => dead.push(i),
+                        MissionResult::Kill(i) => dead.push(i),
                         MissionResult::RemoveItem(pnt) => {
-                            let unit = &world.map[pnt.1][pnt.0];
-                            let mut tiles = unit.tiles.borrow_mut();
-                            tiles[pnt.2] = Tile::Empty;
+                            world.replace(pnt, Tile::Empty);
+                            self.flow.wake(pnt);
+                            self.settling.disturb(pnt);
                         }
                         MissionResult::ReplaceItem(pnt, item) => {
-                            let unit = &world.map[pnt.1][pnt.0];
-                            let mut tiles = unit.tiles.borrow_mut();
-                            tiles[pnt.2] = Tile::Item(item);
+                            world.replace(pnt, Tile::Item(item));
+                            self.flow.wake(pnt);
+                            self.settling.disturb(pnt);
+                        }
+                        MissionResult::PutDown(pnt, item) => {
+                            let put = world.put_down(pnt, item);
+                            if let Some(pnt) = put {
+                                world.take_stock(pnt);
+                                self.flow.wake(pnt);
+                                self.settling.disturb(pnt);
+                            }
+                        }
+                        MissionResult::DrawWater(pnt, depth) => {
+                            liquid::draw_off(world, pnt, depth);
+                            self.flow.wake(pnt);
+                            self.settling.disturb(pnt);
+                        }
+                        MissionResult::Dig(pnt) => {
+                            world.dig(pnt);
+                            world.take_stock(pnt);
+                            self.flow.wake(pnt);
+                            self.settling.disturb(pnt);
+                        }
+                        MissionResult::Build(pnt, tile) => {
+                            world.place(pnt, tile);
+                            world.take_stock(pnt);
+                            self.flow.wake(pnt);
+                            self.settling.disturb(pnt);
                         }
                         _ => (),
                     }
                 }
             }
+            dead.sort();
+            dead.dedup();
+            for i in dead.into_iter().rev() {
+                let (species, pos) = {
+                    let actor = world.life[i].borrow();
+                    (actor.species().species, actor.current_pos())
+                };
+                events.push(WorldEvent::Death(species));
+                world.kill(i);
+                // The body may fall, and fouls any water around it.
+                self.flow.wake(pos);
+                self.settling.disturb(pos);
+            }
         }
+        events
     }
-    /// Updates world time and then deligates to the physics engine.
-    pub fn update(&mut self, time: usize, dt: usize) {
-        self.update_time(time, dt);
-        self.update_life(time);
-        //physics::run(self, dt);
+    /// Advances the world by one tick: updates world time, lets the
+    /// actors act, hands out player orders and then deligates to the
+    /// physics engine. Returns what happened along the way that is
+    /// worth telling the player.
+    pub fn update(&mut self) -> Vec<WorldEvent> {
+        let mut events =
+            self.update_time().into_iter().collect::<Vec<_>>();
+        // The weather moves on once an hour, on the hour.
+        if self.time.clock.time.1 < MINUTES_PER_TICK {
+            weather::run(self);
+        }
+        events.extend(self.update_life());
+        if let Some(ref mut world) = self.map {
+            world.regrow();
+        }
+        events.extend(jobs::run(self));
+        events.extend(physics::run(self));
+        self.tick += 1;
+        events
     }
 
     /// Add a world map and update its meta layer.
@@ -1021,30 +1536,45 @@ impl WorldState {
              .max()
         })
                        .max();
+        // Everything random from here on follows from the seed, so
+        // that the same world plays out the same way every time.
+        self.rng = Streams::new(world.seed());
+        self.weather = Front::default();
+        self.flow = Flow::default();
+        self.fires = Fires::default();
+        self.settling = Settling::default();
         self.map = Some(world);
         self.highest_level = max.unwrap_or(30);
-        self.map
-            .as_mut()
-            .unwrap()
-            .generate_life();
+        let world = self.map.as_mut().unwrap();
+        world.generate_life();
+        world.generate_party();
     }
 
     /// Create a new WorldState, loaded with sensable defaults.
     pub fn new() -> WorldState {
         let clock = Clock { time: (12, 30) };
+        let config = CalendarConfig::default();
+        let days = config.days(START_DATE);
         WorldState {
             commands: vec![],
+            jobs: JobBoard::new(),
             screen: (0, 0),
             level: 31,
             highest_level: 0,
             cursor: (0, 0),
             time: TimeHandler {
-                days: 36512,
-                calendar: Calendar::new(12, 6, 100, &clock),
-                time_of_day: Time::Morning,
+                days: days,
+                calendar: Calendar::new(config, days),
+                time_of_day: Time::from_clock_time(&clock),
                 clock: clock,
             },
             map: None,
+            rng: Streams::new(0),
+            tick: 0,
+            weather: Front::default(),
+            flow: Flow::default(),
+            fires: Fires::default(),
+            settling: Settling::default(),
         }
     }
 }
@@ -0,0 +1,160 @@
+extern crate rand;
+
+use self::rand::Rng;
+
+/// Skews input space onto the simplex grid, and back again, for two
+/// and three dimensions.
+const F2: f32 = 0.366025403;
+const G2: f32 = 0.211324865;
+const F3: f32 = 1.0 / 3.0;
+const G3: f32 = 1.0 / 6.0;
+
+/// Gradients towards the edges of a cube, shared by both dimensions.
+const GRADIENTS: [[f32; 3]; 12] = [[1.0, 1.0, 0.0],
+                                   [-1.0, 1.0, 0.0],
+                                   [1.0, -1.0, 0.0],
+                                   [-1.0, -1.0, 0.0],
+                                   [1.0, 0.0, 1.0],
+                                   [-1.0, 0.0, 1.0],
+                                   [1.0, 0.0, -1.0],
+                                   [-1.0, 0.0, -1.0],
+                                   [0.0, 1.0, 1.0],
+                                   [0.0, -1.0, 1.0],
+                                   [0.0, 1.0, -1.0],
+                                   [0.0, -1.0, -1.0]];
+
+/// Seeded simplex noise in two or three dimensions, with fractal
+/// brownian motion on top. `hurst` and `lacunarity` work as they did
+/// with libtcod's noise: each octave is sampled `lacunarity` times
+/// finer and weighted by the octave's frequency to the `-hurst`.
+pub struct Noise {
+    perm: Vec<usize>,
+    hurst: f32,
+    lacunarity: f32,
+}
+
+impl Noise {
+    pub fn new<R: Rng>(hurst: f32,
+                       lacunarity: f32,
+                       rng: &mut R)
+        -> Noise {
+        let mut perm = (0..256).collect::<Vec<usize>>();
+        rng.shuffle(&mut perm);
+        let doubled = perm.iter()
+                          .chain(perm.iter())
+                          .cloned()
+                          .collect();
+        Noise {
+            perm: doubled,
+            hurst: hurst,
+            lacunarity: lacunarity,
+        }
+    }
+
+    fn gradient(&self, i: isize, j: isize, k: isize) -> &[f32; 3] {
+        let (i, j, k) = ((i & 255) as usize,
+                         (j & 255) as usize,
+                         (k & 255) as usize);
+        &GRADIENTS[self.perm[i + self.perm[j + self.perm[k]]] % 12]
+    }
+
+    /// The contribution of one simplex corner at the given offset.
+    fn corner(radius: f32, offset: &[f32], grad: &[f32; 3]) -> f32 {
+        let t = offset.iter()
+                      .fold(radius, |t, d| t - d * d);
+        if t < 0.0 {
+            0.0
+        } else {
+            let dot = offset.iter()
+                            .zip(grad.iter())
+                            .fold(0.0, |s, (d, g)| s + d * g);
+            t * t * t * t * dot
+        }
+    }
+
+    fn simplex2(&self, x: f32, y: f32) -> f32 {
+        let s = (x + y) * F2;
+        let (i, j) = ((x + s).floor(), (y + s).floor());
+        let t = (i + j) * G2;
+        let (x0, y0) = (x - (i - t), y - (j - t));
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let (i, j) = (i as isize, j as isize);
+        let corners = [([x0, y0], (0, 0)),
+                       ([x0 - i1 as f32 + G2, y0 - j1 as f32 + G2],
+                        (i1, j1)),
+                       ([x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2],
+                        (1, 1))];
+        70.0 *
+            corners.iter().fold(0.0, |n, &(ref offset, (di, dj))| {
+                n +
+                    Self::corner(0.5,
+                                 offset,
+                                 self.gradient(i + di, j + dj, 0))
+            })
+    }
+
+    fn simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let s = (x + y + z) * F3;
+        let (i, j, k) = ((x + s).floor(),
+                         (y + s).floor(),
+                         (z + s).floor());
+        let t = (i + j + k) * G3;
+        let (x0, y0, z0) = (x - (i - t), y - (j - t), z - (k - t));
+        // Which of the six tetrahedra in the cube we are in.
+        let (first, second) = if x0 >= y0 {
+            if y0 >= z0 {
+                ((1, 0, 0), (1, 1, 0))
+            } else if x0 >= z0 {
+                ((1, 0, 0), (1, 0, 1))
+            } else {
+                ((0, 0, 1), (1, 0, 1))
+            }
+        } else {
+            if y0 < z0 {
+                ((0, 0, 1), (0, 1, 1))
+            } else if x0 < z0 {
+                ((0, 1, 0), (0, 1, 1))
+            } else {
+                ((0, 1, 0), (1, 1, 0))
+            }
+        };
+        let (i, j, k) = (i as isize, j as isize, k as isize);
+        [(0, 0, 0), first, second, (1, 1, 1)]
+            .iter()
+            .enumerate()
+            .fold(0.0, |n, (c, &(di, dj, dk))| {
+                let g = c as f32 * G3;
+                let offset = [x0 - di as f32 + g,
+                              y0 - dj as f32 + g,
+                              z0 - dk as f32 + g];
+                let grad = self.gradient(i + di, j + dj, k + dk);
+                n + Self::corner(0.6, &offset, grad)
+            }) * 32.0
+    }
+
+    /// Raw simplex noise at the point, roughly in -1..1. Two or three
+    /// coordinates may be given.
+    pub fn get(&self, coords: &[f32]) -> f32 {
+        match coords.len() {
+            2 => self.simplex2(coords[0], coords[1]),
+            3 => self.simplex3(coords[0], coords[1], coords[2]),
+            n => panic!("simplex noise in {} dimensions", n),
+        }
+    }
+
+    /// Fractal brownian motion: the sum of the given number of
+    /// octaves of noise, clamped to -1..1.
+    pub fn get_fbm(&self, coords: &[f32], octaves: u32) -> f32 {
+        let mut point = coords.to_vec();
+        let mut frequency: f32 = 1.0;
+        let mut value = 0.0;
+        for _ in 0..octaves {
+            value += self.get(&point) * frequency.powf(-self.hurst);
+            frequency *= self.lacunarity;
+            for c in point.iter_mut() {
+                *c *= self.lacunarity;
+            }
+        }
+        value.max(-0.99999).min(0.99999)
+    }
+}
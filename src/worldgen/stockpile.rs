@@ -0,0 +1,38 @@
+use life::Order;
+use utils::{Point2D, Point3D, Rect2D, in_rect};
+use worldgen::terrain::{Item, ItemCategory};
+
+/// A zone of the map that settlers haul loose items into. Each
+/// stockpile keeps track of the items it holds, so that they can be
+/// found again without searching the whole map.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stockpile {
+    pub area: Rect2D,
+    pub filter: Option<ItemCategory>,
+    pub inventory: Vec<(Point3D, Item)>,
+}
+
+impl Stockpile {
+    pub fn new(area: Rect2D, filter: Option<ItemCategory>) -> Stockpile {
+        Stockpile {
+            area: area,
+            filter: filter,
+            inventory: vec![],
+        }
+    }
+
+    /// Tests if the stockpile takes a kind of item. Stockpiles without
+    /// a filter take everything.
+    pub fn accepts(&self, item: &Item) -> bool {
+        self.filter.map_or(true, |c| item.category() == c)
+    }
+
+    pub fn contains(&self, pnt: Point2D) -> bool {
+        in_rect(pnt, self.area)
+    }
+
+    /// The order that hauling jobs for this stockpile are filed under.
+    pub fn order(&self) -> Order {
+        Order::CartGoods(self.area, self.filter)
+    }
+}
@@ -1,20 +1,14 @@
-extern crate tcod;
-extern crate rand;
-
 use std;
 
-use draw::{Describe, DrawChar, FramedDraw};
+use draw::{Backend, BackgroundFlag, Color, Describe, DrawChar,
+           FramedDraw};
+use draw::chars;
 use life::animal::Species;
 use physics::PhysicsActor;
-use tcod::colors::Color;
-use time::Calendar;
+use physics::liquid::{Container, Draught};
 use utils::Point2D;
 use worldgen::Frames;
 
-use tcod::RootConsole;
-use tcod::chars;
-use tcod::console::{BackgroundFlag, Console};
-
 pub const TILES: bool = true;
 pub const BASE: u32 = 256;
 const TILES_BENTGRASS: u32 = BASE;
@@ -50,7 +44,7 @@ const TILES_SNOW: u32 = (BASE + 29);
 
 /////// ROCK
 // Possible igneous rock kinds
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum IgneousRocks {
     Obsidian,
     Basalt,
@@ -68,7 +62,7 @@ impl Describe for IgneousRocks {
 }
 
 impl DrawChar for IgneousRocks {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
             &IgneousRocks::Obsidian => {
                 let chr = if TILES {
@@ -109,7 +103,7 @@ impl DrawChar for IgneousRocks {
 }
 
 // Possible metamorphic rock kinds
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MetamorphicRocks {
     Gneiss,
     Marble,
@@ -129,7 +123,7 @@ impl Describe for MetamorphicRocks {
 }
 
 impl DrawChar for MetamorphicRocks {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
             &MetamorphicRocks::Gneiss => {
                 let chr = if TILES {
@@ -170,7 +164,7 @@ impl DrawChar for MetamorphicRocks {
 }
 
 // Possible Sedimentary rock kinds
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SedimentaryRocks {
     Limestone,
     Conglomerate,
@@ -190,7 +184,7 @@ impl Describe for SedimentaryRocks {
 }
 
 impl DrawChar for SedimentaryRocks {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
             &SedimentaryRocks::Limestone => {
                 let chr = if TILES {
@@ -231,13 +225,15 @@ impl DrawChar for SedimentaryRocks {
 }
 
 // Soil types
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SoilTypes {
     Clay,
     Sandy,
     Silty,
     Peaty,
     Loamy,
+    /// What is left of the ground after a fire.
+    Ash,
 }
 
 impl Describe for SoilTypes {
@@ -248,12 +244,13 @@ impl Describe for SoilTypes {
             &SoilTypes::Silty => "Silty soil".to_string(),
             &SoilTypes::Peaty => "Peaty soil".to_string(),
             &SoilTypes::Loamy => "Loamy soil".to_string(),
+            &SoilTypes::Ash => "Ash".to_string(),
         }
     }
 }
 
 impl DrawChar for SoilTypes {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
             &SoilTypes::Clay => {
                 let chr = if TILES {
@@ -338,13 +335,30 @@ impl DrawChar for SoilTypes {
                                      Color::new(86, 59, 56)
                                  },
                                  Color::new(64, 44, 41))
-            }   
+            }
+            &SoilTypes::Ash => {
+                let chr = if TILES {
+                    std::char::from_u32(TILES_SILTY)
+                        .unwrap()
+                } else {
+                    '='
+                };
+                root.put_char_ex(pos.0 as i32,
+                                 pos.1 as i32,
+                                 chr,
+                                 if TILES {
+                                     Color::new(120, 120, 120)
+                                 } else {
+                                     Color::new(150, 150, 150)
+                                 },
+                                 Color::new(60, 60, 60))
+            }
         }
     }
 }
 
 // Stone types (SCIENCE!)
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum StoneTypes {
     Sedimentary(SedimentaryRocks),
     Igneous(IgneousRocks),
@@ -352,6 +366,19 @@ pub enum StoneTypes {
     Soil(SoilTypes),
 }
 
+impl StoneTypes {
+    /// How hard the stone is to work, from soil being the softest to
+    /// igneous rock being the hardest.
+    pub fn hardness(&self) -> usize {
+        match self {
+            &StoneTypes::Soil(_) => 1,
+            &StoneTypes::Sedimentary(_) => 2,
+            &StoneTypes::Metamorphic(_) => 3,
+            &StoneTypes::Igneous(_) => 4,
+        }
+    }
+}
+
 impl Describe for StoneTypes {
     fn describe(&self) -> String {
         match self {
@@ -364,7 +391,7 @@ impl Describe for StoneTypes {
 }
 
 impl DrawChar for StoneTypes {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
             &StoneTypes::Sedimentary(ref s) => s.draw_char(root, pos),
             &StoneTypes::Metamorphic(ref s) => s.draw_char(root, pos),
@@ -376,7 +403,9 @@ impl DrawChar for StoneTypes {
 
 /////// WATER
 // This is a DF-type game, so... extra fidelty!
-#[derive(Debug, Copy, Clone, PartialEq)]
+// Ordered from cleanest to dirtiest.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord,
+         Serialize, Deserialize)]
 pub enum LiquidPurity {
     // Helps with healing
     Pure,
@@ -392,9 +421,24 @@ pub enum LiquidPurity {
     Toxic,
 }
 
+impl Describe for LiquidPurity {
+    fn describe(&self) -> String {
+        match self {
+            &LiquidPurity::Clean => "clean".to_string(),
+            &LiquidPurity::Clear => "clear".to_string(),
+            &LiquidPurity::Dirty => "dirty".to_string(),
+            &LiquidPurity::Muddy => "muddy".to_string(),
+            &LiquidPurity::Murky => "murky".to_string(),
+            &LiquidPurity::Pure => "pure".to_string(),
+            &LiquidPurity::Sandy => "sandy".to_string(),
+            &LiquidPurity::Toxic => "toxic".to_string(),
+        }
+    }
+}
+
 /////// VEGITATION
 // Vegiatation type, least to most rare, common to least common.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum VegType {
     // Small grasses (height 1)
     Bluegrass,
@@ -416,6 +460,26 @@ pub enum VegType {
     Banyon,
 }
 
+impl VegType {
+    /// Tests if the plant is a tree, which can be felled for wood.
+    pub fn is_tree(&self) -> bool {
+        use self::VegType::*;
+        match self {
+            &Crabapple | &Redbud | &Treetrunk | &Pine | &Redwood |
+            &Banyon => true,
+            _ => false,
+        }
+    }
+
+    /// Tests if the plant is a small herb that can be gathered to eat.
+    pub fn is_herb(&self) -> bool {
+        match self {
+            &VegType::Dandelion | &VegType::Chickweed => true,
+            _ => false,
+        }
+    }
+}
+
 impl Describe for VegType {
     fn describe(&self) -> String {
         match self {
@@ -437,7 +501,7 @@ impl Describe for VegType {
 }
 
 impl DrawChar for VegType {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
             &VegType::Bluegrass => {
                 let chr = if TILES {
@@ -669,9 +733,9 @@ impl DrawChar for VegType {
 
 /////// BIOME
 
-type Ferenheight = f32;
-type Percent = f32;
-#[derive(Debug, Copy, Clone, PartialEq)]
+pub type Ferenheight = f32;
+pub type Percent = f32;
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BiomeType {
     Swamp,
     Jungle,
@@ -698,7 +762,7 @@ impl BiomeType {
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Biome {
     pub biome_type: BiomeType,
     pub temperature_night_f: Ferenheight,
@@ -708,7 +772,7 @@ pub struct Biome {
 
 pub const WATER_BIOME: Biome = Biome {
     biome_type: BiomeType::Water,
-    temperature_night_f: -5.0,
+    temperature_night_f: 45.0,
     temperature_day_f: 70.0,
     percipitation_chance: 80.0,
 };
@@ -760,7 +824,7 @@ impl Biome {
 /////// GENERAL
 // State: the 3 physical forms + fire because it's convenient.
 /// Physical state of an object, based on chemistry.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum State {
     Liquid,
     Solid,
@@ -769,10 +833,13 @@ pub enum State {
 
 /// Descriptive alias (hey, I'm a haskell programmer).
 pub type Height = i32;
+/// How full a tile of liquid is, from 1 (a film) up to `FULL_DEPTH`.
 pub type Depth = i32;
 
+pub const FULL_DEPTH: Depth = 7;
+
 /// North is up, South is down, East is left, West is right.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Compass {
     North,
     South,
@@ -780,8 +847,42 @@ pub enum Compass {
     West,
 }
 
+impl Compass {
+    pub fn opposite(&self) -> Compass {
+        match *self {
+            Compass::North => Compass::South,
+            Compass::South => Compass::North,
+            Compass::East => Compass::West,
+            Compass::West => Compass::East,
+        }
+    }
+
+    /// The point one step this way from another, unless that is off
+    /// the edge of the map at 0.
+    pub fn step(&self, (x, y): Point2D) -> Option<Point2D> {
+        match *self {
+            Compass::North => y.checked_sub(1).map(|y| (x, y)),
+            Compass::South => Some((x, y + 1)),
+            Compass::East => x.checked_sub(1).map(|x| (x, y)),
+            Compass::West => Some((x + 1, y)),
+        }
+    }
+}
+
+impl Describe for Compass {
+    fn describe(&self) -> String {
+        match *self {
+            Compass::North => "north",
+            Compass::South => "south",
+            Compass::East => "east",
+            Compass::West => "west",
+        }
+        .to_string()
+    }
+}
+
 /// Tile types that can be defined to be moveable or as ramps.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RestrictedTile {
     Stone(StoneTypes, State),
     Vegetation(VegType, Height, State),
@@ -807,7 +908,7 @@ impl Describe for RestrictedTile {
 }
 
 impl DrawChar for RestrictedTile {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
             &RestrictedTile::Stone(ref s, State::Solid) => {
                 s.draw_char(root, pos)
@@ -837,7 +938,7 @@ impl DrawChar for RestrictedTile {
 }
 
 /// The
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Tool {
     // Weapons
     Sword,
@@ -862,16 +963,30 @@ pub enum Tool {
     Bowl,
     Goblet,
     Cup,
+    Bucket,
     Plate,
     Wheel,
 }
 
+impl Tool {
+    /// How much liquid the tool holds, which is nothing for anything
+    /// but vessels.
+    pub fn capacity(&self) -> Depth {
+        match *self {
+            Tool::Cup | Tool::Goblet => 1,
+            Tool::Bowl => 2,
+            Tool::Bucket => FULL_DEPTH,
+            _ => 0,
+        }
+    }
+}
+
 impl DrawChar for Tool {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {}
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {}
 }
 
 type Quantity = u8;
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Food {
     Meat(Species),
     Herb(VegType),
@@ -879,7 +994,7 @@ pub enum Food {
 }
 
 impl DrawChar for Food {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
             &Food::Meat(_) => {
                 root.set_default_foreground(Color::new(138, 7, 7));
@@ -901,14 +1016,14 @@ impl DrawChar for Food {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Material {
     Wood(VegType),
     Stone(StoneTypes),
 }
 
 impl DrawChar for Material {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
             &Material::Wood(_) => {
                 root.set_default_foreground(Color::new(139, 69, 19));
@@ -931,26 +1046,101 @@ impl DrawChar for Material {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+impl Describe for Material {
+    fn describe(&self) -> String {
+        match self {
+            &Material::Wood(v) => format!("{} wood", v.describe()),
+            &Material::Stone(s) => s.describe(),
+        }
+    }
+}
+
+/// Kinds of structures that settlers can build.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Structure {
+    Wall,
+    Fence,
+    Ramp,
+    /// A flight of stairs, which unlike the rest can be stood in, and
+    /// climbed to the level above or below.
+    Stairs,
+}
+
+impl Describe for Structure {
+    fn describe(&self) -> String {
+        match self {
+            &Structure::Wall => "wall".to_string(),
+            &Structure::Fence => "fence".to_string(),
+            &Structure::Ramp => "ramp".to_string(),
+            &Structure::Stairs => "stairs".to_string(),
+        }
+    }
+}
+
+impl DrawChar for Structure {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
+        let chr = match self {
+            &Structure::Wall => chars::DCROSS,
+            &Structure::Fence => chars::CROSS,
+            &Structure::Ramp => '^',
+            &Structure::Stairs => 'X',
+        };
+        root.put_char(pos.0 as i32,
+                      pos.1 as i32,
+                      chr,
+                      BackgroundFlag::None);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Magic {
     potency: u8,
     cursed: bool,
-    dates: Option<Calendar>,
+    /// The day, counted from the start of year 0, the magic is tied
+    /// to.
+    dates: Option<usize>,
 }
 
 type Weight = u8;
 type Length = u8;
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Item {
     Tool(Tool, Weight, Length, Option<Magic>),
+    /// A tool that holds liquid, and what is in it.
+    Vessel(Tool, Container<Draught>),
     Food(Food),
     Material(Material),
 }
 
+/// Broad kinds of items, which stockpiles can be limited to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash,
+         Serialize, Deserialize)]
+pub enum ItemCategory {
+    Food,
+    Material,
+    Tool,
+}
+
+impl Item {
+    /// An empty vessel of some kind.
+    pub fn vessel(tool: Tool) -> Item {
+        Item::Vessel(tool, Container::new(tool.capacity()))
+    }
+
+    pub fn category(&self) -> ItemCategory {
+        match self {
+            &Item::Tool(..) | &Item::Vessel(..) => ItemCategory::Tool,
+            &Item::Food(_) => ItemCategory::Food,
+            &Item::Material(_) => ItemCategory::Material,
+        }
+    }
+}
+
 impl DrawChar for Item {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
-            &Item::Tool(t, ..) => t.draw_char(root, pos),
+            &Item::Tool(t, ..) |
+            &Item::Vessel(t, _) => t.draw_char(root, pos),
             &Item::Food(f) => f.draw_char(root, pos),
             &Item::Material(m) => m.draw_char(root, pos),
         }
@@ -967,6 +1157,16 @@ impl Describe for Item {
                     format!("a normal length {} {:?}", l, tk)
                 }
             }
+            &Item::Vessel(tk, vessel) => {
+                match vessel.liquid() {
+                    Some(water) => {
+                        format!("a {:?} of {} water",
+                                tk,
+                                water.purity.describe())
+                    }
+                    None => format!("an empty {:?}", tk),
+                }
+            }
             &Item::Food(f) => format!("a {:?}", f),
             &Item::Material(m) => {
                 format!("some loose, piled {:?}", m)
@@ -978,7 +1178,7 @@ impl Describe for Item {
 /// General types of tiles (very broad) and their current state.
 ///
 /// FIXME: use restricted tile instead of duplication
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Tile {
     Empty,
     Ramp(RestrictedTile),
@@ -987,7 +1187,10 @@ pub enum Tile {
     Water(LiquidPurity, State, Depth),
     Stone(StoneTypes, State),
     Vegetation(VegType, Height, State),
+    Construction(Material, Structure),
     Fire,
+    /// Lying snow, as deep as a liquid of the same depth.
+    Snow(Depth),
 }
 
 impl PhysicsActor for Tile {
@@ -995,6 +1198,8 @@ impl PhysicsActor for Tile {
         match self {
             &Tile::Stone(..) => true,
             &Tile::Ramp(..) => true,
+            &Tile::Construction(_, Structure::Stairs) => false,
+            &Tile::Construction(..) => true,
             _ => false,
         }
     }
@@ -1003,6 +1208,35 @@ impl PhysicsActor for Tile {
         match self {
             &Tile::Stone(..) => true,
             &Tile::Moveable(..) => true,
+            &Tile::Construction(..) => true,
+            &Tile::Item(Item::Material(Material::Stone(_))) => true,
+            &Tile::Item(Item::Tool(..)) |
+            &Tile::Item(Item::Vessel(..)) => true,
+            _ => false,
+        }
+    }
+
+    fn falls_into(&self, below: &Tile) -> bool {
+        let loose = match self {
+            &Tile::Moveable(..) | &Tile::Item(..) => true,
+            _ => false,
+        };
+        loose &&
+            match below {
+                &Tile::Empty => true,
+                &Tile::Water(_, State::Liquid, _) => self.heavy(),
+                _ => false,
+            }
+    }
+
+    fn granular(&self) -> bool {
+        match self {
+            &Tile::Stone(StoneTypes::Soil(SoilTypes::Sandy),
+                         State::Solid) |
+            &Tile::Moveable(RestrictedTile::Stone(
+                StoneTypes::Soil(SoilTypes::Sandy),
+                _,
+            )) => true,
             _ => false,
         }
     }
@@ -1016,22 +1250,16 @@ impl Describe for Tile {
             &Tile::Moveable(ref s) => {
                 format!("Loose pile of {}", s.describe())
             }
-            &Tile::Water(ref purity, ref state, _) => {
-                let purity_str = match purity {
-                    &LiquidPurity::Clean => "clean",
-                    &LiquidPurity::Clear => "clear",
-                    &LiquidPurity::Dirty => "dirty",
-                    &LiquidPurity::Muddy => "muddy",
-                    &LiquidPurity::Murky => "murky",
-                    &LiquidPurity::Pure => "pure",
-                    &LiquidPurity::Sandy => "sandy",
-                    &LiquidPurity::Toxic => "toxic",
-                };
+            &Tile::Water(ref purity, ref state, depth) => {
+                let purity_str = purity.describe();
                 match state {
                     &State::Gas => {
                         format!("Cloud of {} steam", purity_str)
                     }
                     &State::Solid => format!("{} ice", purity_str),
+                    &State::Liquid if depth < FULL_DEPTH => {
+                        format!("Puddle of {} water", purity_str)
+                    }
                     &State::Liquid => format!("{} water", purity_str),
                 }
             }
@@ -1047,6 +1275,17 @@ impl Describe for Tile {
                 }
             }
             &Tile::Fire => "Flames".to_string(),
+            &Tile::Snow(depth) => {
+                match depth {
+                    1...2 => "A dusting of snow",
+                    3...5 => "Snow",
+                    _ => "Deep snow",
+                }
+                .to_string()
+            }
+            &Tile::Construction(m, s) => {
+                format!("{} {}", m.describe(), s.describe())
+            }
             &Tile::Vegetation(veg, ..) => veg.describe(),
             &Tile::Item(i) => i.describe(),
         }
@@ -1054,7 +1293,7 @@ impl Describe for Tile {
 }
 
 impl DrawChar for Tile {
-    fn draw_char(&self, root: &mut RootConsole, pos: Point2D) {
+    fn draw_char(&self, root: &mut Backend, pos: Point2D) {
         match self {
             &Tile::Ramp(_) => {}
             &Tile::Moveable(ref t) => {
@@ -1111,7 +1350,7 @@ impl DrawChar for Tile {
                 };
                 root.put_char_ex(pos.0 as i32,
                                  pos.1 as i32,
-                                 '#',
+                                 chr,
                                  if TILES {
                                      Color::new(255, 255, 255)
                                  } else {
@@ -1119,8 +1358,10 @@ impl DrawChar for Tile {
                                  },
                                  Color::new(100, 255, 100));
             }
-            &Tile::Water(_, State::Liquid, _) => {
-                let chr = if TILES {
+            &Tile::Water(_, State::Liquid, depth) => {
+                let chr = if TILES && depth < FULL_DEPTH {
+                    std::char::from_u32(TILES_PUDDLE).unwrap()
+                } else if TILES {
                     std::char::from_u32(TILES_WATER)
                         .unwrap()
                 } else {
@@ -1171,6 +1412,18 @@ impl DrawChar for Tile {
                                  },
                                  Color::new(255, 0, 0));
             }
+            &Tile::Snow(_) => {
+                let chr = if TILES {
+                    std::char::from_u32(TILES_SNOW).unwrap()
+                } else {
+                    chars::BLOCK1
+                };
+                root.put_char_ex(pos.0 as i32,
+                                 pos.1 as i32,
+                                 chr,
+                                 Color::new(255, 255, 255),
+                                 Color::new(220, 230, 240));
+            }
             &Tile::Empty => {
                 root.put_char_ex(pos.0 as i32,
                                  pos.1 as i32,
@@ -1179,25 +1432,34 @@ impl DrawChar for Tile {
                                  Color::new(150, 150, 150));
             }
             &Tile::Item(i) => i.draw_char(root, pos),
+            &Tile::Construction(m, s) => {
+                let color = match m {
+                    Material::Wood(_) => Color::new(139, 69, 19),
+                    Material::Stone(_) => Color::new(139, 141, 122),
+                };
+                root.set_default_foreground(color);
+                s.draw_char(root, pos);
+                root.set_default_foreground(Color::new(255, 255, 255));
+            }
         }
     }
 }
 
 impl FramedDraw for Tile {
     fn draw_framed_char(&self,
-                        root: &mut RootConsole,
+                        root: &mut Backend,
                         pos: Point2D,
                         time: usize,
                         frames_hash: &Frames) {
-        use self::rand::Rng;
         match self {
-            &Tile::Water(_, State::Liquid, _) => {
+            &Tile::Water(_, State::Liquid, FULL_DEPTH) => {
                 let frames = &frames_hash["Water"];
                 let chr = if TILES {
-                    let rand = *rand::thread_rng()
-                        .choose(&[0, 1, 2])
-                        .unwrap();
-                    let cframe = frames[(time + rand) % frames.len()];
+                    // Offset the animation by position rather than at
+                    // random, so that drawing never touches the
+                    // simulation's random numbers.
+                    let offset = (pos.0 * 7 + pos.1 * 13) % 3;
+                    let cframe = frames[(time + offset) % frames.len()];
                     std::char::from_u32(BASE + cframe as u32)
                         .unwrap()
                 } else {